@@ -1,3 +1,4 @@
+use advent_of_code::helpers::{blocks, parse_lines, parse_value, ParseError};
 use itertools::Itertools;
 
 struct Elf {
@@ -9,31 +10,28 @@ impl Elf {
     }
 }
 
-fn parse_elves(input: &str) -> Vec<Elf> {
-    let split: Vec<Vec<&str>> = input
-        .split("\n\n") // each elf separated by blank line
-        .map(|s| s.trim().split('\n').collect()) // one food item on each line
-        .collect();
-
-    split
-        .iter()
-        .map(|s| Elf {
-            // construct elf from vec of food
-            food: s.iter().map(|x| x.parse::<u32>().unwrap()).collect(), // vec of str to vec of i32
+fn parse_elves(input: &str) -> Result<Vec<Elf>, ParseError> {
+    blocks(input) // each elf separated by blank line
+        .into_iter()
+        .map(|(offset, block)| {
+            Ok(Elf {
+                // one food item on each line
+                food: parse_lines(block, parse_value).map_err(|e| e.offset(offset))?,
+            })
         })
         .collect()
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let elves = parse_elves(input);
-    elves.iter().map(|e| e.total_calories()).max()
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let elves = parse_elves(input)?;
+    Ok(elves.iter().map(|e| e.total_calories()).max().unwrap_or(0))
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let elves = parse_elves(input);
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let elves = parse_elves(input)?;
     let mut calories: Vec<u32> = elves.iter().map(|e| e.total_calories()).collect_vec();
     calories.sort();
-    Some(calories[calories.len() - 3..].iter().sum())
+    Ok(calories.iter().rev().take(3).sum())
 }
 
 fn main() {
@@ -49,12 +47,18 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 1);
-        assert_eq!(part_one(&input), Some(24000));
+        assert_eq!(part_one(&input), Ok(24000));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 1);
-        assert_eq!(part_two(&input), Some(45000));
+        assert_eq!(part_two(&input), Ok(45000));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let err = part_one("1000\n2000\n\n3000\n4x00\n").unwrap_err();
+        assert_eq!((err.line, err.column, err.text.as_str()), (5, 1, "4x00"));
     }
 }
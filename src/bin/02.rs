@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use advent_of_code::helpers::{parse_lines, ParseError};
+
 const WIN: u32 = 6;
 const DRAW: u32 = 3;
 const LOSS: u32 = 0;
@@ -8,7 +10,16 @@ const ROCK: u32 = 1;
 const PAPER: u32 = 2;
 const SCISSORS: u32 = 3;
 
-pub fn part_one(input: &str) -> Option<u32> {
+fn total_score(input: &str, scores: &HashMap<String, u32>) -> Result<u32, ParseError> {
+    let round_scores = parse_lines(input.trim_end(), |s| {
+        scores
+            .get(s)
+            .ok_or_else(|| ParseError::new(s, 1, "expected \"<A|B|C> <X|Y|Z>\""))
+    })?;
+    Ok(round_scores.into_iter().sum())
+}
+
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
     let mut scores = HashMap::new();
     scores.insert(String::from("A X"), ROCK + DRAW); // them Rock, me Rock DRAW
     scores.insert(String::from("A Y"), PAPER + WIN); // them Rock, me Paper WIN
@@ -20,16 +31,10 @@ pub fn part_one(input: &str) -> Option<u32> {
     scores.insert(String::from("C Y"), PAPER + LOSS); // them Scissors, me Paper LOSS
     scores.insert(String::from("C Z"), SCISSORS + DRAW); // them Scissors, me Scissors DRAW
 
-    Some(
-        input
-            .trim()
-            .split('\n')
-            .map(|s| scores.get(s).unwrap())
-            .sum::<u32>(),
-    )
+    total_score(input, &scores)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
     let mut scores = HashMap::new();
     scores.insert(String::from("A X"), SCISSORS + LOSS); // LOSE against Rock, play Scissors
     scores.insert(String::from("A Y"), ROCK + DRAW); // DRAW against Rock, play Rock
@@ -41,13 +46,7 @@ pub fn part_two(input: &str) -> Option<u32> {
     scores.insert(String::from("C Y"), SCISSORS + DRAW); // DRAW against Scissors, play Scissors
     scores.insert(String::from("C Z"), ROCK + WIN); // WIN against Scissors, play Rock
 
-    Some(
-        input
-            .trim()
-            .split('\n')
-            .map(|s| scores.get(s).unwrap())
-            .sum::<u32>(),
-    )
+    total_score(input, &scores)
 }
 
 fn main() {
@@ -63,12 +62,12 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 2);
-        assert_eq!(part_one(&input), Some(15));
+        assert_eq!(part_one(&input), Ok(15));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 2);
-        assert_eq!(part_two(&input), Some(12));
+        assert_eq!(part_two(&input), Ok(12));
     }
 }
@@ -1,50 +1,73 @@
+use advent_of_code::helpers::{parse_lines, ParseError};
 use std::collections::HashSet;
 
 const ALPHABET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
-fn char_to_value(c: char) -> usize {
+fn char_to_value(c: char) -> Option<usize> {
     ALPHABET
         .chars()
         .position(|alph_char| alph_char == c)
-        .unwrap()
-        + 1
+        .map(|idx| idx + 1)
+}
+
+/// the items in a rucksack, checking they're all letters
+fn parse_sack(sack: &str) -> Result<Vec<char>, ParseError> {
+    match sack.chars().position(|c| char_to_value(c).is_none()) {
+        Some(idx) => Err(ParseError::new(
+            sack,
+            idx + 1,
+            "expected an item a-z or A-Z",
+        )),
+        None => Ok(sack.chars().collect()),
+    }
 }
 
 fn compute_score(chars: Vec<char>) -> u32 {
     chars
         .iter()
-        .map(|sack_char| char_to_value(*sack_char))
+        .filter_map(|sack_char| char_to_value(*sack_char))
         .sum::<usize>() as u32
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let matched_chars: Vec<char> = input
-        .lines()
-        .map(|sack| {
-            let left: HashSet<char> = sack[..sack.len() / 2].chars().collect();
-            let right: HashSet<char> = sack[sack.len() / 2..].chars().collect();
-            // first char in left that matches one in right
-            *left.intersection(&right).collect::<Vec<&char>>()[0]
-        })
-        .collect();
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let matched_chars: Vec<char> = parse_lines(input, |sack| {
+        let items = parse_sack(sack)?;
+        let left: HashSet<char> = items[..items.len() / 2].iter().copied().collect();
+        let right: HashSet<char> = items[items.len() / 2..].iter().copied().collect();
+        // first char in left that matches one in right
+        left.intersection(&right)
+            .next()
+            .copied()
+            .ok_or_else(|| ParseError::new(sack, 1, "no item is in both compartments"))
+    })?;
 
-    Some(compute_score(matched_chars))
+    Ok(compute_score(matched_chars))
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
     // I got into a huge fight with referencing, ownership etc. here, and find it
     // very frustrating that you can't just do x.intersection(y).intersection(z)
     // time to readd more of the rust book...
     let mut matched_chars: Vec<char> = Vec::new();
-    for sacks in input.lines().collect::<Vec<&str>>().chunks(3) {
-        let mut badge: HashSet<char> = sacks[0].chars().collect();
+    let sacks = parse_lines(input, parse_sack)?;
+    for (group, sacks) in sacks.chunks(3).enumerate() {
+        let mut badge: HashSet<char> = sacks[0].iter().copied().collect();
         for next_sack in &sacks[1..] {
-            badge.retain(|x| next_sack.contains(*x));
+            badge.retain(|x| next_sack.contains(x));
+        }
+        match badge.into_iter().next() {
+            Some(badge) => matched_chars.push(badge),
+            None => {
+                let line = input.lines().nth(3 * group).unwrap_or("");
+                return Err(
+                    ParseError::new(line, 1, "no item is in all three rucksacks")
+                        .offset(3 * group + 1),
+                );
+            }
         }
-        matched_chars.push(badge.into_iter().collect::<Vec<char>>()[0]);
     }
 
-    Some(compute_score(matched_chars))
+    Ok(compute_score(matched_chars))
 }
 
 fn main() {
@@ -60,12 +83,21 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 3);
-        assert_eq!(part_one(&input), Some(157));
+        assert_eq!(part_one(&input), Ok(157));
+
+        assert_eq!(
+            part_one("vJrwpWtwJgWr\nab1d").unwrap_err().to_string(),
+            "line 2, column 3: expected an item a-z or A-Z (in \"ab1d\")"
+        );
+        assert_eq!(part_one("abcd").unwrap_err().column, 1);
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 3);
-        assert_eq!(part_two(&input), Some(70));
+        assert_eq!(part_two(&input), Ok(70));
+
+        let input = format!("{input}\nabc\ndef\nghi");
+        assert_eq!(part_two(&input).unwrap_err().line, 7);
     }
 }
@@ -1,4 +1,4 @@
-use itertools::Itertools;
+use advent_of_code::helpers::{parse_lines, ParseError, Template};
 
 struct Elf {
     low: u32,
@@ -16,28 +16,25 @@ impl Elf {
     }
 }
 
-fn parse_elves(input: &str) -> Vec<Vec<Elf>> {
-    input
-        .lines()
-        .map(|line| {
-            line.split(',')
-                .map(|elf| {
-                    let elf_values = elf
-                        .split('-')
-                        .map(|digit| digit.parse::<u32>().unwrap())
-                        .collect_vec();
-                    Elf {
-                        low: elf_values[0],
-                        high: elf_values[1],
-                    }
-                })
-                .collect_vec()
-        })
-        .collect_vec()
+fn parse_elves(input: &str) -> Result<Vec<Vec<Elf>>, ParseError> {
+    let template = Template::new("{}-{},{}-{}");
+    parse_lines(input, |line| {
+        let matches = template.captures(line)?;
+        Ok(vec![
+            Elf {
+                low: matches.get(0)?,
+                high: matches.get(1)?,
+            },
+            Elf {
+                low: matches.get(2)?,
+                high: matches.get(3)?,
+            },
+        ])
+    })
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let elf_pairs = parse_elves(input);
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let elf_pairs = parse_elves(input)?;
     let contained_pairs = elf_pairs.iter().fold(0, |total, pair| {
         if pair[0].contains(&pair[1]) || pair[1].contains(&pair[0]) {
             total + 1
@@ -45,11 +42,11 @@ pub fn part_one(input: &str) -> Option<u32> {
             total
         }
     });
-    Some(contained_pairs)
+    Ok(contained_pairs)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let elf_pairs = parse_elves(input);
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let elf_pairs = parse_elves(input)?;
     let overlapping_pairs = elf_pairs.iter().fold(0, |total, pair| {
         if pair[0].overlaps(&pair[1]) {
             total + 1
@@ -57,7 +54,7 @@ pub fn part_two(input: &str) -> Option<u32> {
             total
         }
     });
-    Some(overlapping_pairs)
+    Ok(overlapping_pairs)
 }
 
 fn main() {
@@ -73,12 +70,17 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 4);
-        assert_eq!(part_one(&input), Some(2));
+        assert_eq!(part_one(&input), Ok(2));
+
+        assert_eq!(
+            part_one("2-4,6-8\n2-3,4_5").unwrap_err().to_string(),
+            "line 2, column 5: expected \"-\" (in \"2-3,4_5\")"
+        );
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 4);
-        assert_eq!(part_two(&input), Some(4));
+        assert_eq!(part_two(&input), Ok(4));
     }
 }
@@ -1,92 +1,271 @@
-use std::collections::VecDeque;
-
+use advent_of_code::helpers::{blocks, parse_lines, ParseError, Template};
+use advent_of_code::Normalize;
 use itertools::Itertools;
-use regex::Regex;
 
 struct Instruction {
     n: usize,
-    origin: usize,
-    destination: usize,
+    origin: usize,      // label of the stack to take crates from
+    destination: usize, // label of the stack to put them on
+}
+fn parse_instruction(template: &Template, instr: &str) -> Result<Instruction, ParseError> {
+    let matches = template.captures(instr)?;
+    Ok(Instruction {
+        n: matches.get(0)?,
+        origin: matches.get(1)?,
+        destination: matches.get(2)?,
+    })
 }
-fn parse_instruction(instr: &str) -> Instruction {
-    let re = Regex::new(r"move (\d+) from (\d+) to (\d+)").unwrap();
-    let matches = &re.captures_iter(instr).collect_vec()[0];
-    Instruction {
-        n: matches[1].parse::<usize>().unwrap(),
-        origin: matches[2].parse::<usize>().unwrap(),
-        destination: matches[3].parse::<usize>().unwrap(),
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.n, self.origin, self.destination
+        )
     }
 }
 
-fn parse_input(input: &str) -> (Vec<VecDeque<char>>, Vec<Instruction>) {
-    // separate input into stacks and instructions
-    let sections = input.split("\n\n").collect_vec();
-
-    // parse stacks
-    let mut stacks = Vec::<VecDeque<char>>::new();
-    for lin in sections[0].lines() {
-        let mut column: usize = 0;
-        for (cursor, chr) in lin.chars().enumerate() {
-            if chr.is_numeric() {
-                // last line (column labels)
-                break;
-            }
-            if cursor % 4 == 0 {
-                // new column every 4 characters (format is: [A]_ )
-                // check we have enough vectors and initiate a new one if not
-                if stacks.len() <= column {
-                    stacks.push(VecDeque::<char>::new())
-                }
-                column += 1;
+/// how a crane puts down the crates it lifted in one instruction
+trait CraneModel {
+    /// `lifted` is in stack order (bottom to top) as taken off the origin stack,
+    /// rearrange it to the order it will have on the destination stack
+    fn arrange(&self, lifted: &mut [char]);
+}
+
+/// moves one crate at a time, so the lifted crates end up upside down
+struct CrateMover9000;
+impl CraneModel for CrateMover9000 {
+    fn arrange(&self, lifted: &mut [char]) {
+        lifted.reverse();
+    }
+}
+
+/// moves all the crates at once, keeping their order
+struct CrateMover9001;
+impl CraneModel for CrateMover9001 {
+    fn arrange(&self, _lifted: &mut [char]) {}
+}
+
+/// flips the crates after another crane model has arranged them
+struct Reversed<C: CraneModel>(C);
+impl<C: CraneModel> CraneModel for Reversed<C> {
+    fn arrange(&self, lifted: &mut [char]) {
+        self.0.arrange(lifted);
+        lifted.reverse();
+    }
+}
+
+struct Ship {
+    labels: Vec<usize>,
+    stacks: Vec<Vec<char>>, // crates in each stack, bottom to top
+}
+impl Ship {
+    fn stack_idx(&self, label: usize) -> Option<usize> {
+        self.labels.iter().position(|l| *l == label)
+    }
+
+    fn apply(&mut self, instr: &Instruction, crane: &impl CraneModel) -> Result<(), String> {
+        let origin = self
+            .stack_idx(instr.origin)
+            .ok_or(format!("no stack labelled {}", instr.origin))?;
+        let destination = self
+            .stack_idx(instr.destination)
+            .ok_or(format!("no stack labelled {}", instr.destination))?;
+        let height = self.stacks[origin].len();
+        if instr.n > height {
+            return Err(format!(
+                "can't move {} crates from stack {} with {height} crates",
+                instr.n, instr.origin
+            ));
+        }
+        // remove number of requested crates from the top of the origin stack
+        let mut lifted = self.stacks[origin].split_off(height - instr.n);
+        crane.arrange(&mut lifted);
+        // then put them on top of the destination stack
+        self.stacks[destination].extend(lifted);
+        Ok(())
+    }
+
+    /// crate at the top of each stack (stacks that are empty are skipped)
+    fn tops(&self) -> String {
+        self.stacks.iter().filter_map(|s| s.last()).collect()
+    }
+}
+
+fn parse_ship(drawing: &str) -> Result<Ship, ParseError> {
+    let lines = drawing.lines().collect_vec();
+    let Some((label_line, crate_lines)) = lines.split_last() else {
+        return Err(ParseError::new(drawing, 1, "expected a stack drawing"));
+    };
+
+    // the last line holds the labels, find where each one is (char index range)
+    let chars = label_line.chars().collect_vec();
+    let mut spans = Vec::<(usize, usize)>::new();
+    let mut labels = Vec::<usize>::new();
+    let mut cursor = 0;
+    while cursor < chars.len() {
+        if chars[cursor].is_whitespace() {
+            cursor += 1;
+            continue;
+        }
+        let start = cursor;
+        while (cursor < chars.len()) && !chars[cursor].is_whitespace() {
+            cursor += 1;
+        }
+        let label: String = chars[start..cursor].iter().collect();
+        let label = label.parse::<usize>().map_err(|e| {
+            ParseError::new(
+                label_line,
+                start + 1,
+                format!("could not parse stack label \"{label}\": {e}"),
+            )
+            .offset(lines.len())
+        })?;
+        spans.push((start, cursor));
+        labels.push(label);
+    }
+
+    // crates are drawn as [A], assign each one to the label it is drawn above
+    let mut stacks = vec![Vec::<char>::new(); labels.len()];
+    for (line_idx, line) in crate_lines.iter().enumerate().rev() {
+        let chars = line.chars().collect_vec();
+        for (cursor, chr) in chars.iter().enumerate() {
+            if !chr.is_alphabetic() {
+                continue;
             }
-            if chr.is_alphabetic() {
-                // add this crate's character to the stack
-                stacks[column - 1].push_back(chr);
+            if (cursor == 0) || (chars[cursor - 1] != '[') || (chars.get(cursor + 1) != Some(&']'))
+            {
+                return Err(
+                    ParseError::new(line, cursor + 1, "expected crate as \"[A]\"")
+                        .offset(line_idx + 1),
+                );
             }
+            // distance (x2) from the crate to the middle of each label
+            let column = spans
+                .iter()
+                .position_min_by_key(|(start, end)| (2 * cursor).abs_diff(start + end - 1))
+                .ok_or_else(|| {
+                    ParseError::new(line, cursor + 1, "crate has no stack label")
+                        .offset(line_idx + 1)
+                })?;
+            stacks[column].push(*chr);
         }
     }
-    // parse instructions
-    let instructions = sections[1].lines().map(parse_instruction).collect_vec();
 
-    (stacks, instructions)
+    Ok(Ship { labels, stacks })
 }
 
-pub fn part_one(input: &str) -> Option<String> {
-    let (mut stacks, instructions) = parse_input(input);
-    for instr in instructions {
-        let mut moved = VecDeque::<char>::new();
-        // remove number of requested characters from the origin stack
-        for _ in 0..instr.n {
-            moved.push_front(stacks[instr.origin - 1].pop_front().unwrap());
+impl std::fmt::Display for Ship {
+    /// draw the stacks in the same format as the puzzle input
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let width = self
+            .labels
+            .iter()
+            .map(|l| l.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row = self
+                .stacks
+                .iter()
+                .map(|s| match s.get(level) {
+                    Some(c) => format!("{:<width$}", format!("[{c}]")),
+                    None => " ".repeat(width),
+                })
+                .join(" ");
+            writeln!(f, "{row}")?;
         }
-        // then append them to the destination stack
-        moved.append(&mut stacks[instr.destination - 1]);
-        stacks[instr.destination - 1] = moved;
+        let labels = self
+            .labels
+            .iter()
+            .map(|l| format!("{:^width$}", l))
+            .join(" ");
+        write!(f, "{labels}")
     }
+}
 
-    Some(stacks.iter().map(|s| s[0]).collect::<String>())
+fn parse_input(input: &str) -> Result<(Ship, Vec<Instruction>), ParseError> {
+    // separate input into stacks and instructions
+    let sections = blocks(input);
+    if sections.len() != 2 {
+        return Err(ParseError::new(
+            input.lines().last().unwrap_or(""),
+            1,
+            format!(
+                "expected stacks and instructions separated by a blank line, found {} sections",
+                sections.len()
+            ),
+        )
+        .offset(input.lines().count()));
+    }
+
+    let (offset, drawing) = sections[0];
+    let ship = parse_ship(drawing).map_err(|e| e.offset(offset))?;
+
+    // parse instructions
+    let template = Template::new("move {} from {} to {}");
+    let (offset, text) = sections[1];
+    let instructions = parse_lines(text, |instr| parse_instruction(&template, instr))
+        .map_err(|e| e.offset(offset))?;
+
+    Ok((ship, instructions))
 }
 
-pub fn part_two(input: &str) -> Option<String> {
-    // the only difference between this and part_one is moved.push_back instead
-    // of moved.push_front in the for loop below (will tidy up later)
-    let (mut stacks, instructions) = parse_input(input);
-    for instr in instructions {
-        let mut moved = VecDeque::<char>::new();
-        // remove number of requested characters from the origin stack
-        for _ in 0..instr.n {
-            moved.push_back(stacks[instr.origin - 1].pop_front().unwrap());
-        }
-        // then append them to the destination stack
-        moved.append(&mut stacks[instr.destination - 1]);
-        stacks[instr.destination - 1] = moved;
+/// parse the input and carry out the first `steps` instructions (all of them if None)
+fn run(input: &str, crane: &impl CraneModel, steps: Option<usize>) -> Result<Ship, ParseError> {
+    let (mut ship, instructions) = parse_input(input)?;
+    // line number of the first instruction, for errors
+    let first_line = blocks(input)[1].0 + 1;
+    for (idx, instr) in instructions
+        .iter()
+        .take(steps.unwrap_or(instructions.len()))
+        .enumerate()
+    {
+        ship.apply(instr, crane).map_err(|message| {
+            ParseError::new(&instr.to_string(), 1, message).offset(first_line + idx)
+        })?;
     }
+    Ok(ship)
+}
+
+pub fn part_one(input: &str) -> Result<String, ParseError> {
+    Ok(run(input, &CrateMover9000, None)?.tops())
+}
 
-    Some(stacks.iter().map(|s| s[0]).collect::<String>())
+pub fn part_two(input: &str) -> Result<String, ParseError> {
+    Ok(run(input, &CrateMover9001, None)?.tops())
 }
 
 fn main() {
-    let input = &advent_of_code::read_file("inputs", 5);
+    // `cargo solve 5 -- --reversed` also shows the tops if each crane flipped the
+    // crates it moved
+    let reversed = pico_args::Arguments::from_env().contains("--reversed");
+    let input = &advent_of_code::read_file_with("inputs", 5, Normalize::LINE_ENDINGS_ONLY);
+    if reversed {
+        let ships = [
+            (
+                "CrateMover 9000",
+                run(input, &Reversed(CrateMover9000), None),
+            ),
+            (
+                "CrateMover 9001",
+                run(input, &Reversed(CrateMover9001), None),
+            ),
+        ];
+        for (name, ship) in ships {
+            match ship {
+                Ok(ship) => println!("reversed {name}: {}", ship.tops()),
+                Err(e) => println!(
+                    "reversed {name}: invalid input for day {}: {}",
+                    env!("CARGO_BIN_NAME"),
+                    e
+                ),
+            }
+        }
+    }
     advent_of_code::solve!(1, part_one, input);
     advent_of_code::solve!(2, part_two, input);
 }
@@ -97,13 +276,47 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let input = advent_of_code::read_file("examples", 5);
-        assert_eq!(part_one(&input), Some(String::from("CMZ")));
+        let input = advent_of_code::read_file_with("examples", 5, Normalize::LINE_ENDINGS_ONLY);
+        assert_eq!(part_one(&input), Ok(String::from("CMZ")));
     }
 
     #[test]
     fn test_part_two() {
-        let input = advent_of_code::read_file("examples", 5);
-        assert_eq!(part_two(&input), Some(String::from("MCD")));
+        let input = advent_of_code::read_file_with("examples", 5, Normalize::LINE_ENDINGS_ONLY);
+        assert_eq!(part_two(&input), Ok(String::from("MCD")));
+    }
+
+    #[test]
+    fn test_render() {
+        let input = advent_of_code::read_file_with("examples", 5, Normalize::LINE_ENDINGS_ONLY);
+        let drawing = blocks(&input)[0].1;
+        assert_eq!(
+            run(&input, &CrateMover9000, Some(0)).unwrap().to_string(),
+            drawing
+        );
+        assert_eq!(
+            run(&input, &CrateMover9000, Some(1)).unwrap().to_string(),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        // the 9001 with its crates flipped behaves like the 9000
+        assert_eq!(
+            run(&input, &Reversed(CrateMover9001), None).unwrap().tops(),
+            "CMZ"
+        );
+    }
+
+    #[test]
+    fn test_wide_labels() {
+        let ship = Ship {
+            labels: (1..=11).collect(),
+            stacks: (0..11)
+                .map(|i| vec![(b'A' + i as u8) as char; i % 3])
+                .collect(),
+        };
+        let drawing = ship.to_string();
+        let parsed = parse_ship(&drawing).unwrap();
+        assert_eq!(parsed.labels, ship.labels);
+        assert_eq!(parsed.stacks, ship.stacks);
+        assert_eq!(parsed.to_string(), drawing);
     }
 }
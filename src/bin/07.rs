@@ -1,101 +1,253 @@
-use std::{collections::HashMap, path::PathBuf};
+use advent_of_code::helpers::{ParseError, Template};
 
-struct File {
-    size: u32,
+const ROOT: usize = 0; // index of "/" in the filesystem arena
+
+enum Kind {
+    Dir(Vec<usize>), // indices of children
+    File(u32),       // size
+}
+
+struct Node {
+    name: String,
+    parent: Option<usize>,
+    kind: Kind,
 }
-struct Dir {
-    children: Vec<PathBuf>,
-    files: Vec<File>,
-    size: u32,
+
+/// a directory picked by one of the disk space queries
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirSize {
+    pub path: String,
+    pub size: u32,
 }
 
-fn parse_input(input: &str) -> HashMap<PathBuf, Dir> {
-    let mut tree = HashMap::new();
-
-    // route to current directory: last element is current directory,
-    // elements before are directories traversed to get there
-    let mut cd: Vec<PathBuf> = Vec::new();
-
-    for line in input.lines() {
-        let parts = line.split(' ').collect::<Vec<&str>>();
-        match parts[0] {
-            "$" => {
-                // this is a command
-                match parts[1] {
-                    "cd" => match parts[2] {
-                        ".." => {
-                            // move up to parent directory
-                            cd.pop();
-                        }
-                        _ => {
-                            // create and move into child directory
-                            // cd[cd.len() - 1] is the current directory
-                            // parts[2] is the relative name of the new directory
-                            if cd.is_empty() {
-                                cd.push(PathBuf::from(parts[2])); // if this is thhe first dir no previous path to join
-                            } else {
-                                cd.push(cd[cd.len() - 1].join(parts[2]));
-                            }
-
-                            tree.insert(
-                                cd[cd.len() - 1].to_path_buf(),
-                                Dir {
-                                    children: Vec::<PathBuf>::new(),
-                                    files: Vec::<File>::new(),
-                                    size: 0,
-                                },
-                            );
-                        }
-                    },
-                    _ => continue, // ls: nothing to do, will parse listed files in next iterations
-                };
+/// why a disk space query couldn't be answered
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    Overfull { used: u32, disk: u32 }, // the files don't fit on the disk
+    NoneBigEnough { to_free: u32 },    // no directory frees enough space on its own
+    EnoughSpace { free: u32 },         // nothing needs deleting
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::Overfull { used, disk } => {
+                write!(f, "{used} bytes used on a disk of size {disk}")
             }
-            "dir" => {
-                // add listed directory to current directory's children
-                // cd[cd.len() - 1] is the current directory
-                tree.get_mut(&cd[cd.len() - 1])
-                    .unwrap()
-                    .children
-                    .push(cd[cd.len() - 1].join(parts[1]));
+            Error::NoneBigEnough { to_free } => {
+                write!(f, "no directory is big enough to free {to_free} bytes")
             }
-            _ => {
-                // this is a file size + file name
-                let f = File {
-                    size: parts[0].parse::<u32>().unwrap(),
-                };
-                // add the file's size to all the directory sizes in the current hierarchy
-                for dir in &cd {
-                    tree.get_mut(dir).unwrap().size += f.size;
-                }
-                // add the file to the current directory's files
-                tree.get_mut(&cd[cd.len() - 1]).unwrap().files.push(f);
+            Error::EnoughSpace { free } => {
+                write!(f, "{free} bytes are free already, nothing needs deleting")
             }
         }
     }
-    tree
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let tree = parse_input(input);
-    Some(tree.values().fold(0, |sum, dir| {
-        if dir.size <= 100000 {
-            sum + dir.size
-        } else {
-            sum
+/// in-memory filesystem, all files and directories are stored in one arena vec
+/// and refer to each other by index
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+impl FileSystem {
+    fn new() -> FileSystem {
+        FileSystem {
+            nodes: vec![Node {
+                name: String::from("/"),
+                parent: None,
+                kind: Kind::Dir(Vec::new()),
+            }],
+        }
+    }
+
+    fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        match &self.nodes[dir].kind {
+            Kind::Dir(children) => children
+                .iter()
+                .copied()
+                .find(|c| self.nodes[*c].name == name),
+            Kind::File(_) => None,
+        }
+    }
+
+    /// add a file or directory to dir, or return the existing one if it was already
+    /// listed (so listing a directory twice doesn't count anything twice)
+    fn add(&mut self, dir: usize, name: &str, kind: Kind) -> usize {
+        if let Some(existing) = self.child(dir, name) {
+            return existing;
         }
-    }))
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(dir),
+            kind,
+        });
+        if let Kind::Dir(children) = &mut self.nodes[dir].kind {
+            children.push(idx);
+        }
+        idx
+    }
+
+    fn is_dir(&self, idx: usize) -> bool {
+        matches!(self.nodes[idx].kind, Kind::Dir(_))
+    }
+
+    /// total size of every directory (indexed like `nodes`, 0 for files)
+    fn dir_sizes(&self) -> Vec<u32> {
+        let mut sizes = vec![0; self.nodes.len()];
+        for node in &self.nodes {
+            if let Kind::File(size) = node.kind {
+                // add the file's size to all the directories above it
+                let mut parent = node.parent;
+                while let Some(dir) = parent {
+                    sizes[dir] += size;
+                    parent = self.nodes[dir].parent;
+                }
+            }
+        }
+        sizes
+    }
+
+    /// full path of a file or directory, e.g. "/a/e"
+    fn path(&self, idx: usize) -> String {
+        match self.nodes[idx].parent {
+            None => String::from("/"),
+            Some(ROOT) => format!("/{}", self.nodes[idx].name),
+            Some(parent) => format!("{}/{}", self.path(parent), self.nodes[idx].name),
+        }
+    }
+
+    /// all directories with size at most `threshold`
+    pub fn dirs_at_most(&self, threshold: u32) -> Vec<DirSize> {
+        let sizes = self.dir_sizes();
+        (0..self.nodes.len())
+            .filter(|idx| self.is_dir(*idx) && (sizes[*idx] <= threshold))
+            .map(|idx| DirSize {
+                path: self.path(idx),
+                size: sizes[idx],
+            })
+            .collect()
+    }
+
+    /// sum of the sizes of all directories with size at most `threshold`
+    pub fn total_size_at_most(&self, threshold: u32) -> u32 {
+        self.dirs_at_most(threshold).iter().map(|d| d.size).sum()
+    }
+
+    /// total size of every file on the disk
+    pub fn used(&self) -> u32 {
+        self.dir_sizes()[ROOT]
+    }
+
+    /// smallest directory that could be deleted to have `needed` space free on a
+    /// disk of size `disk` (None if there's enough free space already)
+    pub fn smallest_to_free(&self, disk: u32, needed: u32) -> Result<Option<DirSize>, Error> {
+        let sizes = self.dir_sizes();
+        let used = sizes[ROOT];
+        let free = disk
+            .checked_sub(used)
+            .ok_or(Error::Overfull { used, disk })?;
+        let to_free = match needed.checked_sub(free) {
+            Some(to_free) if to_free > 0 => to_free,
+            _ => return Ok(None),
+        };
+        (0..self.nodes.len())
+            .filter(|idx| self.is_dir(*idx) && (sizes[*idx] >= to_free))
+            .min_by_key(|idx| sizes[*idx])
+            .map(|idx| {
+                Some(DirSize {
+                    path: self.path(idx),
+                    size: sizes[idx],
+                })
+            })
+            .ok_or(Error::NoneBigEnough { to_free })
+    }
+
+    fn render(&self, idx: usize, depth: usize, text: &mut String) {
+        let node = &self.nodes[idx];
+        let desc = match &node.kind {
+            Kind::Dir(_) => String::from("dir"),
+            Kind::File(size) => format!("file, size={size}"),
+        };
+        text.push_str(&format!("{}- {} ({desc})\n", "  ".repeat(depth), node.name));
+        if let Kind::Dir(children) = &node.kind {
+            for child in children {
+                self.render(*child, depth + 1, text);
+            }
+        }
+    }
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let tree = parse_input(input);
-    let space_needed = 30000000 - (70000000 - tree.get(&PathBuf::from("/")).unwrap().size);
-    Some(tree.values().fold(u32::MAX, |size, dir| {
-        if (dir.size >= space_needed) & (dir.size < size) {
-            dir.size
+impl std::fmt::Display for FileSystem {
+    /// `tree`-style listing in the format used by the puzzle text
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut text = String::new();
+        self.render(ROOT, 0, &mut text);
+        write!(f, "{}", text.trim_end())
+    }
+}
+
+/// rebuild the filesystem from a terminal transcript of `cd` and `ls` commands
+pub fn replay(input: &str) -> Result<FileSystem, ParseError> {
+    let mut fs = FileSystem::new();
+    let mut cwd = ROOT;
+    let mut listing = false; // whether lines are currently output from `ls`
+    let file = Template::new("{} {}");
+
+    for (idx, line) in input.lines().enumerate() {
+        let err =
+            |column: usize, message: &str| ParseError::new(line, column, message).offset(idx + 1);
+
+        if let Some(command) = line.strip_prefix("$ ") {
+            listing = false;
+            match command.split_once(' ') {
+                Some(("cd", "/")) => cwd = ROOT,
+                Some(("cd", "..")) => cwd = fs.nodes[cwd].parent.unwrap_or(ROOT),
+                Some(("cd", name)) => {
+                    // allow moving into directories that haven't been listed yet
+                    let dir = fs.add(cwd, name, Kind::Dir(Vec::new()));
+                    if !fs.is_dir(dir) {
+                        return Err(err(6, "can't cd into a file"));
+                    }
+                    cwd = dir;
+                }
+                None if command == "ls" => listing = true,
+                _ => return Err(err(3, "unknown command")),
+            }
+        } else if !listing {
+            return Err(err(1, "expected a command"));
+        } else if let Some(name) = line.strip_prefix("dir ") {
+            fs.add(cwd, name, Kind::Dir(Vec::new()));
         } else {
-            size
+            // this is a file size + file name
+            let matches = file.captures(line).map_err(|e| e.offset(idx + 1))?;
+            let size = matches.get(0).map_err(|e| e.offset(idx + 1))?;
+            fs.add(cwd, matches.str(1), Kind::File(size));
         }
-    }))
+    }
+    Ok(fs)
+}
+
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let fs = replay(input)?;
+    Ok(fs.total_size_at_most(100000))
+}
+
+pub fn part_two(input: &str) -> Result<u32, Error> {
+    let fs = replay(input)?;
+    let (disk, needed) = (70000000, 30000000);
+    match fs.smallest_to_free(disk, needed)? {
+        Some(dir) => Ok(dir.size),
+        None => Err(Error::EnoughSpace {
+            free: disk - fs.used(),
+        }),
+    }
 }
 
 fn main() {
@@ -111,12 +263,90 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 7);
-        assert_eq!(part_one(&input), Some(95437));
+        assert_eq!(part_one(&input), Ok(95437));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 7);
-        assert_eq!(part_two(&input), Some(24933642));
+        assert_eq!(part_two(&input), Ok(24933642));
+
+        // 70000000 - 20000000 bytes free is more than the 30000000 needed
+        assert_eq!(
+            part_two("$ cd /\n$ ls\n20000000 a.txt"),
+            Err(Error::EnoughSpace { free: 50000000 })
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let input = advent_of_code::read_file("examples", 7);
+        // listing directories twice and jumping back to the root shouldn't change anything
+        let repeated =
+            format!("{input}$ cd /\n$ ls\ndir a\n14848514 b.txt\n$ cd a\n$ ls\n29116 f\n");
+        let fs = replay(&repeated).unwrap();
+        assert_eq!(fs.total_size_at_most(100000), 95437);
+        assert_eq!(
+            fs.to_string(),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)"
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let input = advent_of_code::read_file("examples", 7);
+        let fs = replay(&input).unwrap();
+        let dir = |path: &str, size| DirSize {
+            path: path.to_string(),
+            size,
+        };
+
+        assert_eq!(
+            fs.dirs_at_most(100000),
+            vec![dir("/a", 94853), dir("/a/e", 584)]
+        );
+        assert_eq!(fs.total_size_at_most(1000), 584);
+        assert_eq!(
+            fs.smallest_to_free(70000000, 30000000),
+            Ok(Some(dir("/d", 24933642)))
+        );
+        // a full disk of the same size as the files needs any 90000 bytes freeing
+        assert_eq!(
+            fs.smallest_to_free(48381165, 90000),
+            Ok(Some(dir("/a", 94853)))
+        );
+        // 21618835 bytes are free already
+        assert_eq!(fs.smallest_to_free(70000000, 10000000), Ok(None));
+        assert_eq!(fs.smallest_to_free(70000000, 21618835), Ok(None));
+        assert_eq!(
+            fs.smallest_to_free(70000000, 21618836),
+            Ok(Some(dir("/a/e", 584)))
+        );
+        assert_eq!(
+            fs.smallest_to_free(40000000, 0),
+            Err(Error::Overfull {
+                used: 48381165,
+                disk: 40000000
+            })
+        );
+        assert_eq!(
+            fs.smallest_to_free(48381165, 48381166)
+                .unwrap_err()
+                .to_string(),
+            "no directory is big enough to free 48381166 bytes"
+        );
     }
 }
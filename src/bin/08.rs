@@ -1,16 +1,32 @@
-fn parse_grid(input: &str) -> Vec<Vec<u32>> {
-    input
-        .lines()
-        .map(|row| {
-            row.chars()
-                .map(|c| c.to_digit(10).unwrap())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>()
+use advent_of_code::helpers::{parse_lines, ParseError};
+
+/// tree heights, checking every row is the same (non-zero) length
+fn parse_grid(input: &str) -> Result<Vec<Vec<u32>>, ParseError> {
+    let mut width = None;
+    let grid = parse_lines(input, |row| {
+        if row.is_empty() || (*width.get_or_insert(row.len()) != row.len()) {
+            return Err(ParseError::new(
+                row,
+                1,
+                "expected rows of the same number of trees",
+            ));
+        }
+        row.chars()
+            .enumerate()
+            .map(|(idx, c)| {
+                c.to_digit(10)
+                    .ok_or_else(|| ParseError::new(row, idx + 1, "expected a height 0-9"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    if grid.is_empty() {
+        return Err(ParseError::new(input, 1, "expected a grid of trees"));
+    }
+    Ok(grid)
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let grid = parse_grid(input);
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let grid = parse_grid(input)?;
     let nrows = grid.len();
     let ncols = grid[0].len();
     let mut visible = vec![vec![false; ncols]; nrows];
@@ -54,16 +70,14 @@ pub fn part_one(input: &str) -> Option<u32> {
             }
         }
     }
-    Some(
-        visible
-            .iter()
-            .map(|row| row.iter().filter(|col| **col).count())
-            .sum::<usize>() as u32,
-    )
+    Ok(visible
+        .iter()
+        .map(|row| row.iter().filter(|col| **col).count())
+        .sum::<usize>() as u32)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let grid = parse_grid(input);
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let grid = parse_grid(input)?;
     let nrows = grid.len();
     let ncols = grid[0].len();
     let mut scenic_score = vec![vec![1; ncols]; nrows];
@@ -134,13 +148,11 @@ pub fn part_two(input: &str) -> Option<u32> {
         }
     }
 
-    Some(
-        *scenic_score
-            .iter()
-            .map(|row| row.iter().max().unwrap())
-            .max()
-            .unwrap() as u32,
-    )
+    Ok(*scenic_score
+        .iter()
+        .map(|row| row.iter().max().unwrap())
+        .max()
+        .unwrap() as u32)
 }
 
 fn main() {
@@ -156,12 +168,19 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 8);
-        assert_eq!(part_one(&input), Some(21));
+        assert_eq!(part_one(&input), Ok(21));
+
+        assert_eq!(
+            part_one("303\n2x5").unwrap_err().to_string(),
+            "line 2, column 2: expected a height 0-9 (in \"2x5\")"
+        );
+        assert_eq!(part_one("303\n25").unwrap_err().line, 2);
+        assert!(part_one("").is_err());
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 8);
-        assert_eq!(part_two(&input), Some(8));
+        assert_eq!(part_two(&input), Ok(8));
     }
 }
@@ -1,79 +1,179 @@
-use itertools::Itertools;
-use std::cmp::Ordering;
+use advent_of_code::helpers::{parse_lines, ParseError, Template};
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
 
-fn is_adjacent(h_pos: (i32, i32), t_pos: (i32, i32)) -> bool {
-    if ((h_pos.0 - t_pos.0).abs() <= 1) & ((h_pos.1 - t_pos.1).abs() <= 1) {
-        return true;
-    }
-    false
+type Point = (i32, i32); // (x, y) with y increasing upwards
+
+fn is_adjacent(h_pos: Point, t_pos: Point) -> bool {
+    ((h_pos.0 - t_pos.0).abs() <= 1) && ((h_pos.1 - t_pos.1).abs() <= 1)
 }
 
-fn move_tail(h_pos: (i32, i32), t_pos: (i32, i32)) -> (i32, i32) {
+fn move_tail(h_pos: Point, t_pos: Point) -> Point {
     if !is_adjacent(h_pos, t_pos) {
+        // one step towards the head in each direction it's not level in
         (
-            t_pos.0
-                + match h_pos.0.cmp(&t_pos.0) {
-                    Ordering::Greater => 1,
-                    Ordering::Less => -1,
-                    Ordering::Equal => 0,
-                },
-            t_pos.1
-                + match h_pos.1.cmp(&t_pos.1) {
-                    Ordering::Greater => 1,
-                    Ordering::Less => -1,
-                    Ordering::Equal => 0,
-                },
+            t_pos.0 + (h_pos.0 - t_pos.0).signum(),
+            t_pos.1 + (h_pos.1 - t_pos.1).signum(),
         )
     } else {
         t_pos
     }
 }
 
-fn move_rope(pos: &mut Vec<(i32, i32)>, dir: &str, n: u32, visited: &mut [HashSet<(i32, i32)>]) {
-    let delta = match dir {
-        "U" => (-1, 0),
-        "D" => (1, 0),
-        "L" => (0, -1),
-        "R" => (0, 1),
-        _ => panic!("unknown direction"),
-    };
-    for _ in 0..n {
-        // move head
-        pos[0] = (pos[0].0 + delta.0, pos[0].1 + delta.1);
-        // move remaining knots
-        for knot in 1..pos.len() {
-            (pos[knot].0, pos[knot].1) = move_tail(pos[knot - 1], pos[knot]);
-            visited[knot].insert(pos[knot]);
+/// one line of the input: move the head `steps` times by (dx, dy)
+struct Move {
+    name: String, // as written in the input, e.g. "R 4"
+    delta: Point,
+    steps: u32,
+}
+
+fn parse_moves(input: &str) -> Result<Vec<Move>, ParseError> {
+    let template = Template::new("{} {}");
+    parse_lines(input, |line| {
+        let matches = template.captures(line)?;
+        let delta = match matches.str(0) {
+            "U" => (0, 1),
+            "D" => (0, -1),
+            "L" => (-1, 0),
+            "R" => (1, 0),
+            "UL" => (-1, 1),
+            "UR" => (1, 1),
+            "DL" => (-1, -1),
+            "DR" => (1, -1),
+            _ => {
+                return Err(ParseError::new(
+                    line,
+                    1,
+                    "unknown direction, expected U, D, L, R, UL, UR, DL or DR",
+                ))
+            }
+        };
+        Ok(Move {
+            name: line.to_string(),
+            delta,
+            steps: matches.get(1)?,
+        })
+    })
+}
+
+struct Rope {
+    knots: Vec<Point>,            // current position of each knot, head first
+    visited: Vec<HashSet<Point>>, // unique positions each knot has visited
+}
+impl Rope {
+    /// a rope with all its knots at the start
+    fn new(n_knots: usize) -> Rope {
+        Rope {
+            knots: vec![(0, 0); n_knots],
+            visited: vec![HashSet::from([(0, 0)]); n_knots],
+        }
+    }
+
+    fn apply(&mut self, mv: &Move) {
+        for _ in 0..mv.steps {
+            // move head
+            self.knots[0] = (self.knots[0].0 + mv.delta.0, self.knots[0].1 + mv.delta.1);
+            self.visited[0].insert(self.knots[0]);
+            // move remaining knots
+            for knot in 1..self.knots.len() {
+                self.knots[knot] = move_tail(self.knots[knot - 1], self.knots[knot]);
+                self.visited[knot].insert(self.knots[knot]);
+            }
         }
     }
+
+    /// smallest area containing every position any knot has visited
+    fn bounds(&self) -> (RangeInclusive<i32>, RangeInclusive<i32>) {
+        let points = self.visited.iter().flatten();
+        let x_min = points.clone().map(|p| p.0).min().unwrap_or(0);
+        let x_max = points.clone().map(|p| p.0).max().unwrap_or(0);
+        let y_min = points.clone().map(|p| p.1).min().unwrap_or(0);
+        let y_max = points.map(|p| p.1).max().unwrap_or(0);
+        (x_min..=x_max, y_min..=y_max)
+    }
+
+    fn draw(
+        &self,
+        xs: &RangeInclusive<i32>,
+        ys: &RangeInclusive<i32>,
+        cell: impl Fn(Point) -> char,
+    ) -> String {
+        let rows = ys
+            .clone()
+            .rev()
+            .map(|y| xs.clone().map(|x| cell((x, y))).collect::<String>())
+            .collect::<Vec<_>>();
+        rows.join("\n")
+    }
+
+    /// the rope in the area given like the puzzle text: H for the head, then the knot
+    /// number (or T if there's only a tail), s for the start. Where knots overlap the
+    /// one nearest the head is shown.
+    fn render(&self, xs: &RangeInclusive<i32>, ys: &RangeInclusive<i32>) -> String {
+        self.draw(xs, ys, |p| match self.knots.iter().position(|k| *k == p) {
+            Some(0) => 'H',
+            Some(_) if self.knots.len() == 2 => 'T',
+            Some(knot) => char::from_digit(knot as u32 % 10, 10).unwrap(),
+            None if p == (0, 0) => 's',
+            None => '.',
+        })
+    }
+
+    /// the positions a knot has visited in the area given, as # (s for the start)
+    fn render_visited(
+        &self,
+        knot: usize,
+        xs: &RangeInclusive<i32>,
+        ys: &RangeInclusive<i32>,
+    ) -> String {
+        self.draw(xs, ys, |p| match p {
+            (0, 0) => 's',
+            _ if self.visited[knot].contains(&p) => '#',
+            _ => '.',
+        })
+    }
 }
 
-fn count_tail_visited(input: &str, knots: u32) -> u32 {
-    // current position of each knot
-    let mut pos = vec![(0, 0); knots as usize];
-    // unique positions each knot has visited
-    let mut visited = vec![HashSet::<(i32, i32)>::new(); knots as usize];
-    for instr in input.lines() {
-        let parts = instr.split(' ').collect_vec();
-        let dir = parts[0]; // direction to move
-        let n = parts[1].parse::<u32>().unwrap(); // number of steps to take
-        move_rope(&mut pos, dir, n, &mut visited);
+/// move a rope with `n_knots` knots, calling `inspect` with the index of each move
+/// and the rope after it
+fn simulate(moves: &[Move], n_knots: usize, mut inspect: impl FnMut(usize, &Rope)) -> Rope {
+    let mut rope = Rope::new(n_knots);
+    for (idx, mv) in moves.iter().enumerate() {
+        rope.apply(mv);
+        inspect(idx, &rope);
     }
+    rope
+}
+
+fn count_tail_visited(input: &str, knots: usize) -> Result<u32, ParseError> {
+    let rope = simulate(&parse_moves(input)?, knots, |_, _| {});
     // number of positions visited by last knot
-    visited[visited.len() - 1].len() as u32
+    Ok(rope.visited[knots - 1].len() as u32)
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    Some(count_tail_visited(input, 2))
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    count_tail_visited(input, 2)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    Some(count_tail_visited(input, 10))
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    count_tail_visited(input, 10)
 }
 
 fn main() {
+    // `cargo solve 9 -- --draw 3` draws the 10 knot rope after each of the first 3 moves,
+    // then the positions its tail visited
+    let draw: Option<usize> = pico_args::Arguments::from_env()
+        .opt_value_from_str("--draw")
+        .unwrap();
     let input = &advent_of_code::read_file("inputs", 9);
+    if let (Some(n_moves), Ok(moves)) = (draw, parse_moves(input)) {
+        let moves = &moves[..n_moves.min(moves.len())];
+        let (xs, ys) = simulate(moves, 10, |_, _| {}).bounds();
+        let rope = simulate(moves, 10, |idx, rope| {
+            println!("== {} ==\n\n{}\n", moves[idx].name, rope.render(&xs, &ys));
+        });
+        println!("{}\n", rope.render_visited(9, &xs, &ys));
+    }
     advent_of_code::solve!(1, part_one, input);
     advent_of_code::solve!(2, part_two, input);
 }
@@ -86,17 +186,68 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 9);
-        assert_eq!(part_one(&input), Some(13));
+        assert_eq!(part_one(&input), Ok(13));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 9);
-        assert_eq!(part_two(&input), Some(1));
+        assert_eq!(part_two(&input), Ok(1));
 
         let cwd = env::current_dir().unwrap();
         let filepath = cwd.join("src").join("examples").join("09b.txt");
         let input2 = fs::read_to_string(filepath).unwrap();
-        assert_eq!(part_two(&input2), Some(36));
+        assert_eq!(part_two(&input2), Ok(36));
+    }
+
+    #[test]
+    fn test_render() {
+        // the area drawn in the puzzle text
+        let (xs, ys) = (0..=5, 0..=4);
+        let input = advent_of_code::read_file("examples", 9);
+        let moves = parse_moves(&input).unwrap();
+
+        let mut frames = Vec::new();
+        let rope = simulate(&moves, 2, |idx, rope| {
+            frames.push(format!(
+                "== {} ==\n{}",
+                moves[idx].name,
+                rope.render(&xs, &ys)
+            ));
+        });
+        assert_eq!(
+            frames[0],
+            "== R 4 ==\n......\n......\n......\n......\ns..TH."
+        );
+        assert_eq!(
+            frames[1],
+            "== U 4 ==\n....H.\n....T.\n......\n......\ns....."
+        );
+        assert_eq!(
+            rope.render_visited(1, &xs, &ys),
+            "..##..\n...##.\n.####.\n....#.\ns###.."
+        );
+        assert_eq!(rope.bounds(), (0..=5, 0..=4));
+
+        let rope = simulate(&moves[..1], 10, |_, _| {});
+        assert_eq!(rope.render(&xs, &ys).lines().last(), Some("4321H."));
+        assert_eq!(
+            rope.visited.iter().map(|v| v.len()).collect::<Vec<_>>(),
+            vec![5, 4, 3, 2, 1, 1, 1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_moves() {
+        // diagonal moves drag the tail along diagonally
+        let moves = parse_moves("UR 3\nDL 1").unwrap();
+        let rope = simulate(&moves, 2, |_, _| {});
+        assert_eq!(rope.knots, vec![(2, 2), (2, 2)]);
+        assert_eq!(rope.visited[1].len(), 3);
+
+        assert_eq!(
+            parse_moves("R 4\nX 2").err().unwrap().to_string(),
+            "line 2, column 1: unknown direction, expected U, D, L, R, UL, UR, DL or DR (in \"X 2\")"
+        );
     }
 }
@@ -1,61 +1,167 @@
-use itertools::Itertools;
-
-fn update(
-    cycle: &mut i32,
-    x: &mut i32,
-    signal: &mut i32,
-    display: &mut String,
-    store_cycles: &[i32],
-) {
-    *cycle += 1;
-    if store_cycles.contains(cycle) {
-        *signal += (*x) * (*cycle);
+use advent_of_code::helpers::{ocr, parse_lines, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+}
+impl Instruction {
+    /// number of cycles the instruction takes to complete
+    fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(value) => write!(f, "addx {value}"),
+        }
+    }
+}
+
+fn parse_program(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    parse_lines(input, |line| match line.split_once(' ') {
+        None if line == "noop" => Ok(Instruction::Noop),
+        Some(("addx", value)) => value
+            .parse()
+            .map(Instruction::Addx)
+            .map_err(|e| ParseError::new(line, 6, format!("could not parse \"{value}\": {e}"))),
+        _ => Err(ParseError::new(line, 1, "unknown instruction")),
+    })
+}
+
+/// the CPU during one cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tick {
+    cycle: i32,               // starting from 1
+    x: i32,                   // value of the X register during the cycle
+    instruction: Instruction, // being executed
+}
+
+/// runs a program, yielding the state during each cycle until the program ends
+struct Cpu<'a> {
+    program: &'a [Instruction],
+    pc: usize,      // index of the instruction being executed
+    elapsed: usize, // cycles spent on that instruction so far
+    cycle: i32,
+    x: i32,
+}
+impl<'a> Cpu<'a> {
+    fn new(program: &'a [Instruction]) -> Cpu<'a> {
+        Cpu {
+            program,
+            pc: 0,
+            elapsed: 0,
+            cycle: 0,
+            x: 1,
+        }
     }
-    let pixel: i32 = (*cycle - 1) % 40;
-    if (pixel - *x).abs() <= 1 {
-        *display += "■";
-    } else {
-        *display += " ";
+
+    /// run until `breakpoint` returns true for a cycle, and return that cycle (None if
+    /// the program ended first). The CPU can be resumed from the next cycle afterwards.
+    fn run_until(&mut self, mut breakpoint: impl FnMut(&Tick) -> bool) -> Option<Tick> {
+        self.find(|tick| breakpoint(tick))
     }
-    if *cycle % 40 == 0 {
-        *display += "\n";
+
+    /// the states during the given cycles only
+    fn sample(self, cycles: &'a [i32]) -> impl Iterator<Item = Tick> + 'a {
+        self.filter(|tick| cycles.contains(&tick.cycle))
     }
 }
 
-pub fn run(input: &str) -> (i32, String) {
-    let mut cycle = 0;
-    let mut x = 1;
-    let store_cycles = vec![20, 60, 100, 140, 180, 220];
-    let mut signal = 0;
-    let mut display: String = "".to_string();
-    for instr in input.lines() {
-        update(&mut cycle, &mut x, &mut signal, &mut display, &store_cycles);
-        match instr {
-            "noop" => {
-                continue;
-            }
-            _ => {
-                // addx
-                update(&mut cycle, &mut x, &mut signal, &mut display, &store_cycles);
-                x += instr.split(' ').collect_vec()[1].parse::<i32>().unwrap();
+impl Iterator for Cpu<'_> {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        // finish the instruction if it took up all its cycles
+        if let Some(instruction) = self.program.get(self.pc) {
+            if self.elapsed == instruction.cycles() {
+                if let Instruction::Addx(value) = instruction {
+                    self.x += value;
+                }
+                self.pc += 1;
+                self.elapsed = 0;
             }
         }
+        let instruction = *self.program.get(self.pc)?;
+        self.cycle += 1;
+        self.elapsed += 1;
+        Some(Tick {
+            cycle: self.cycle,
+            x: self.x,
+            instruction,
+        })
     }
-    (signal, display)
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let (signal, _) = run(input);
-    Some(signal as u32)
+/// sum of cycle * x during the given cycles
+fn signal_strength(ticks: impl Iterator<Item = Tick>) -> i32 {
+    ticks.map(|tick| tick.cycle * tick.x).sum()
 }
 
-pub fn part_two(input: &str) -> Option<String> {
-    let (_, display) = run(input);
-    Some(display)
+/// the CRT screen, a row of 40 pixels at a time. The pixel for each cycle is lit if
+/// the 3 pixel wide sprite centred on x covers it.
+fn pixels(ticks: impl Iterator<Item = Tick>) -> Vec<Vec<bool>> {
+    let mut screen = Vec::new();
+    for tick in ticks {
+        let pixel: i32 = (tick.cycle - 1) % 40;
+        if pixel == 0 {
+            screen.push(Vec::new());
+        }
+        if let Some(row) = screen.last_mut() {
+            row.push((pixel - tick.x).abs() <= 1);
+        }
+    }
+    screen
+}
+
+/// draw the CRT screen with ■ for lit pixels
+fn draw(screen: &[Vec<bool>]) -> String {
+    screen
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|lit| if *lit { '■' } else { ' ' })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+pub fn part_one(input: &str) -> Result<i32, ParseError> {
+    let program = parse_program(input)?;
+    let cycles = [20, 60, 100, 140, 180, 220];
+    Ok(signal_strength(Cpu::new(&program).sample(&cycles)))
+}
+
+pub fn part_two(input: &str) -> Result<String, ParseError> {
+    let program = parse_program(input)?;
+    ocr(&pixels(Cpu::new(&program)))
 }
 
 fn main() {
+    // `cargo solve 10 -- --break 20` shows the CPU state during cycle 20, and `--draw`
+    // shows the screen part two reads the letters from
+    let mut args = pico_args::Arguments::from_env();
+    let breakpoint: Option<i32> = args.opt_value_from_str("--break").unwrap();
     let input = &advent_of_code::read_file("inputs", 10);
+    if let (true, Ok(program)) = (args.contains("--draw"), parse_program(input)) {
+        println!("{}", draw(&pixels(Cpu::new(&program))));
+    }
+    if let (Some(cycle), Ok(program)) = (breakpoint, parse_program(input)) {
+        match Cpu::new(&program).run_until(|tick| tick.cycle == cycle) {
+            Some(tick) => println!(
+                "cycle {cycle}: x = {}, executing {}",
+                tick.x, tick.instruction
+            ),
+            None => println!("the program ends before cycle {cycle}"),
+        }
+    }
     advent_of_code::solve!(1, part_one, input);
     advent_of_code::solve!(2, part_two, input);
 }
@@ -63,22 +169,96 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code::helpers::FONT;
 
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 10);
-        assert_eq!(part_one(&input), Some(13140));
+        assert_eq!(part_one(&input), Ok(13140));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 10);
+        let program = parse_program(&input).unwrap();
+        assert_eq!(
+            draw(&pixels(Cpu::new(&program))),
+            "■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  \n■■■   ■■■   ■■■   ■■■   ■■■   ■■■   ■■■ \n■■■■    ■■■■    ■■■■    ■■■■    ■■■■    \n■■■■■     ■■■■■     ■■■■■     ■■■■■     \n■■■■■■      ■■■■■■      ■■■■■■      ■■■■\n■■■■■■■       ■■■■■■■       ■■■■■■■     \n"
+        );
+        // the example doesn't draw letters
+        assert!(part_two(&input).is_err());
+    }
+
+    #[test]
+    fn test_letters() {
+        // a program drawing "ECHO". Each addx takes two cycles, so it sets x for the
+        // next two pixels: on the first pixel (x = p - 1), the second (x = p + 2),
+        // both (x = p + 1) or neither (x = p + 4). x starts at 1 so "E" fits.
+        let word = "ECHO".chars().collect::<Vec<_>>();
+        let lit = |pixel: i32| {
+            let (row, col) = ((pixel / 40) as usize, (pixel % 40) as usize);
+            let glyph = word
+                .get(col / 5)
+                .and_then(|c| FONT.iter().find(|f| f.0 == *c));
+            match glyph {
+                Some((_, pattern)) if col % 5 < 4 => pattern.as_bytes()[row * 4 + col % 5] == b'#',
+                _ => false,
+            }
+        };
+        let xs = (0..240)
+            .step_by(2)
+            .map(|pixel| {
+                let p = pixel % 40;
+                match (lit(pixel), lit(pixel + 1)) {
+                    (true, true) => p + 1,
+                    (true, false) => p - 1,
+                    (false, true) => p + 2,
+                    (false, false) => p + 4,
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(xs[0], 1);
+        let program = xs
+            .iter()
+            .zip(xs.iter().skip(1).chain([xs.last().unwrap()]))
+            .map(|(x, next)| format!("addx {}", next - x))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(part_two(&program), Ok(String::from("ECHO")));
+    }
+
+    #[test]
+    fn test_cpu() {
+        // the small program from the puzzle text
+        let program = parse_program("noop\naddx 3\naddx -5").unwrap();
+        let trace = Cpu::new(&program)
+            .map(|tick| (tick.cycle, tick.x))
+            .collect::<Vec<_>>();
+        assert_eq!(trace, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+
+        // stop at a breakpoint, then carry on
+        let mut cpu = Cpu::new(&program);
+        let tick = cpu.run_until(|tick| tick.x == 4).unwrap();
+        assert_eq!(tick.cycle, 4);
+        assert_eq!(tick.instruction.to_string(), "addx -5");
+        assert_eq!(cpu.next().map(|tick| tick.cycle), Some(5));
+        assert_eq!(cpu.run_until(|_| true), None);
+
+        let input = advent_of_code::read_file("examples", 10);
+        let program = parse_program(&input).unwrap();
+        let samples = Cpu::new(&program)
+            .sample(&[20, 220, 500])
+            .map(|tick| tick.x)
+            .collect::<Vec<_>>();
+        assert_eq!(samples, vec![21, 18]);
+
+        assert_eq!(
+            parse_program("noop\nmulx 2").err().unwrap().to_string(),
+            "line 2, column 1: unknown instruction (in \"mulx 2\")"
+        );
         assert_eq!(
-            part_two(&input),
-            Some(
-                "■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  ■■  \n■■■   ■■■   ■■■   ■■■   ■■■   ■■■   ■■■ \n■■■■    ■■■■    ■■■■    ■■■■    ■■■■    \n■■■■■     ■■■■■     ■■■■■     ■■■■■     \n■■■■■■      ■■■■■■      ■■■■■■      ■■■■\n■■■■■■■       ■■■■■■■       ■■■■■■■     \n"
-                .to_string()
-            )
+            parse_program("addx two").err().unwrap().to_string(),
+            "line 1, column 6: could not parse \"two\": invalid digit found in string (in \"addx two\")"
         );
     }
 }
@@ -1,158 +1,354 @@
+use advent_of_code::helpers::{blocks, ParseError, Template};
 use itertools::Itertools;
+use std::collections::HashMap;
+
+/// worry levels are widened so that squaring a u64 level can't overflow, and all the
+/// arithmetic on them is checked
+type Worry = u128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Old,        // the worry level before the operation
+    Const(u64), // a fixed number
+}
+impl Operand {
+    fn value(&self, old: Worry) -> Worry {
+        match self {
+            Operand::Old => old,
+            Operand::Const(value) => *value as Worry,
+        }
+    }
+}
+
+/// how a monkey changes an item's worry level when inspecting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Add(Operand, Operand),
+    Mul(Operand, Operand),
+}
+impl Operation {
+    /// new worry level, None if it overflows
+    fn apply(&self, old: Worry) -> Option<Worry> {
+        match self {
+            Operation::Add(lhs, rhs) => lhs.value(old).checked_add(rhs.value(old)),
+            Operation::Mul(lhs, rhs) => lhs.value(old).checked_mul(rhs.value(old)),
+        }
+    }
+}
+
+/// how worry levels drop after each inspection, when an item isn't damaged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relief {
+    Divide(u64), // divided by this (non-zero) number, rounding down
+    None,        // worry levels stay the same
+}
+impl Relief {
+    fn apply(&self, worry: Worry) -> Worry {
+        match self {
+            Relief::Divide(by) => worry / *by as Worry,
+            Relief::None => worry,
+        }
+    }
+}
+
+/// why a simulation couldn't be carried out
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    Overflow(String), // what was being worked out when a number got too big
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::Overflow(what) => write!(f, "overflow in {what}"),
+        }
+    }
+}
 
 struct Monkey {
-    items: Vec<u64>,
-    operation: String,
-    test: String,
+    items: Vec<Worry>,
+    operation: Operation,
+    divisor: u64,    // items with worry divisible by this go to if_true
+    if_true: usize,  // index of the monkey to throw to if divisible
+    if_false: usize, // index of the monkey to throw to otherwise
     inspect_count: u64,
 }
 impl Monkey {
-    fn inspect(&mut self, part1: bool, max_worry: u64) {
-        // run operation
-        let terms = self
-            .operation
-            .trim()
-            .strip_prefix("Operation: new = ")
-            .unwrap()
-            .split(' ')
-            .collect_vec();
+    /// inspect every item, keeping worry levels modulo `modulus` if given. None if a
+    /// worry level overflows.
+    fn inspect(&mut self, relief: Relief, modulus: Option<Worry>) -> Option<()> {
         for worry in &mut self.items {
             // perform inspections
-            let lhs = match terms[0] {
-                "old" => *worry,
-                _ => terms[0].parse::<u64>().unwrap(),
-            };
-            let rhs = match terms[2] {
-                "old" => *worry,
-                _ => terms[2].parse::<u64>().unwrap(),
-            };
-            *worry = match terms[1] {
-                "*" => lhs * rhs,
-                "+" => lhs + rhs,
-                _ => panic!("unknown operation"),
-            };
-            if part1 {
-                *worry /= 3; // divide worry by 3 after inspection
+            *worry = relief.apply(self.operation.apply(*worry)?);
+            if let Some(modulus) = modulus {
+                *worry %= modulus;
             }
-            *worry %= max_worry; // avoid overflow using modulo arithmetic
             self.inspect_count += 1;
         }
+        Some(())
     }
-    fn throw(&mut self) -> Vec<(usize, u64)> {
-        let test_lines = self
-            .test
-            .strip_prefix("Test: divisible by ")
-            .unwrap()
-            .lines()
-            .collect_vec();
-
-        let div_by = test_lines[0].parse::<u64>().unwrap();
-
-        let true_idx = test_lines[1]
-            .trim()
-            .strip_prefix("If true: throw to monkey ")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
-        let false_idx = test_lines[2]
-            .trim()
-            .strip_prefix("If false: throw to monkey ")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
-        let thrown_items = self
-            .items
-            .iter()
-            .map(|worry| {
-                if *worry % div_by == 0 {
-                    (true_idx, *worry)
-                } else {
-                    (false_idx, *worry)
-                }
-            })
-            .collect_vec();
-        self.items = Vec::<u64>::new(); // empty items as we throw them all to others
-
-        thrown_items
-    }
-}
-
-fn parse_monkeys(input: &str) -> Vec<Monkey> {
-    let groups = input.split("\n\n").collect_vec();
-    let mut monkeys = Vec::<Monkey>::new();
-    for g in groups {
-        let lines = g.lines().collect_vec();
-        let items = lines[1]
-            .trim()
-            .strip_prefix("Starting items: ")
-            .unwrap()
-            .split(", ")
-            .map(|x| x.parse::<u64>().unwrap())
-            .collect_vec();
-        let operation = lines[2].trim().to_string();
-        let test = lines[3..].join("\n").trim().to_string();
-        monkeys.push(Monkey {
-            items,
-            operation,
-            test,
-            inspect_count: 0,
-        });
+
+    /// which monkey an item with this worry level is thrown to
+    fn target(&self, worry: Worry) -> usize {
+        if worry.is_multiple_of(self.divisor as Worry) {
+            self.if_true
+        } else {
+            self.if_false
+        }
     }
+
+    fn throw(&mut self) -> Vec<(usize, Worry)> {
+        let items = std::mem::take(&mut self.items); // empty items as we throw them all to others
+        items
+            .into_iter()
+            .map(|worry| (self.target(worry), worry))
+            .collect_vec()
+    }
+}
+
+/// product of all the monkeys' divisors: whether a worry level is divisible by any of
+/// them only depends on the worry level modulo this
+fn common_modulus(monkeys: &[Monkey]) -> Result<Worry, Error> {
     monkeys
+        .iter()
+        .try_fold(1 as Worry, |acc, m| acc.checked_mul(m.divisor as Worry))
+        .ok_or(Error::Overflow(String::from("the product of the divisors")))
 }
 
-fn run(input: &str, part1: bool) -> u64 {
-    let mut monkeys = parse_monkeys(input);
+fn parse_monkey(block: &str) -> Result<Monkey, ParseError> {
+    let lines = block.lines().collect_vec();
+    let templates = [
+        "Monkey {}:",
+        "  Starting items: {}",
+        "  Operation: new = {} {} {}",
+        "  Test: divisible by {}",
+        "    If true: throw to monkey {}",
+        "    If false: throw to monkey {}",
+    ]
+    .map(Template::new);
+    if lines.len() != templates.len() {
+        return Err(ParseError::new(
+            lines[lines.len() - 1],
+            1,
+            format!("expected {} lines per monkey", templates.len()),
+        )
+        .offset(lines.len()));
+    }
+    let captures = |idx: usize| {
+        templates[idx]
+            .captures(lines[idx])
+            .map_err(|e| e.offset(idx + 1))
+    };
+
+    let items = captures(1)?
+        .list::<Worry>(0, ", ")
+        .map_err(|e| e.offset(2))?;
+
+    let matches = captures(2)?;
+    let operand = |term: usize| {
+        matches
+            .get::<u64>(term)
+            .map(Operand::Const)
+            .or_else(|e| match matches.str(term) {
+                "old" => Ok(Operand::Old),
+                _ => Err(e.offset(3)),
+            })
+    };
+    let (lhs, rhs) = (operand(0)?, operand(2)?);
+    let operation = match matches.str(1) {
+        "+" => Operation::Add(lhs, rhs),
+        "*" => Operation::Mul(lhs, rhs),
+        _ => {
+            return Err(ParseError::new(lines[2], 1, "expected operator \"*\" or \"+\"").offset(3))
+        }
+    };
 
-    // will use worry values module common factor of all divisors to avoid overflow
-    let max_worry: u64 = monkeys
+    let divisor = captures(3)?.get(0).map_err(|e| e.offset(4))?;
+    if divisor == 0 {
+        return Err(ParseError::new(lines[3], 1, "can't test divisibility by 0").offset(4));
+    }
+    Ok(Monkey {
+        items,
+        operation,
+        divisor,
+        if_true: captures(4)?.get(0).map_err(|e| e.offset(5))?,
+        if_false: captures(5)?.get(0).map_err(|e| e.offset(6))?,
+        inspect_count: 0,
+    })
+}
+
+fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    let blocks = blocks(input);
+    let monkeys = blocks
         .iter()
-        .map(|m| {
-            // parse divide by value from test string
-            m.test
-                .strip_prefix("Test: divisible by ")
-                .unwrap()
-                .lines()
-                .collect_vec()[0]
-                .parse::<u64>()
-                .unwrap()
-        })
-        .product();
+        .map(|(offset, block)| parse_monkey(block).map_err(|e| e.offset(*offset)))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let rounds = if part1 { 20 } else { 10000 };
-    for _ in 0..rounds {
+    // every monkey must throw to one that exists
+    for ((offset, block), monkey) in blocks.iter().zip(&monkeys) {
+        for (line_idx, target) in [(4, monkey.if_true), (5, monkey.if_false)] {
+            if target >= monkeys.len() {
+                let line = block.lines().nth(line_idx).unwrap_or("");
+                return Err(ParseError::new(
+                    line,
+                    line.len() - target.to_string().len() + 1,
+                    format!("there is no monkey {target}"),
+                )
+                .offset(offset + line_idx + 1));
+            }
+        }
+    }
+    Ok(monkeys)
+}
+
+/// number of items each monkey inspects over `rounds` rounds, taking every monkey's
+/// turn in order
+fn simulate(monkeys: &mut [Monkey], rounds: u64, relief: Relief) -> Result<Vec<u64>, Error> {
+    // without relief, worry levels can be kept modulo the divisors' product to stay
+    // small. Dividing them doesn't work modulo anything, so then use them in full.
+    let modulus = match relief {
+        Relief::None => Some(common_modulus(monkeys)?),
+        Relief::Divide(_) => None,
+    };
+
+    for round in 1..=rounds {
         for monkey_idx in 0..monkeys.len() {
-            monkeys[monkey_idx].inspect(part1, max_worry);
+            monkeys[monkey_idx]
+                .inspect(relief, modulus)
+                .ok_or_else(|| {
+                    Error::Overflow(format!("round {round}, monkey {monkey_idx}'s operation"))
+                })?;
             let thrown_items = monkeys[monkey_idx].throw();
             for item in thrown_items {
                 monkeys[item.0].items.push(item.1);
             }
         }
     }
-    let counts = monkeys
+    Ok(monkeys.iter().map(|m| m.inspect_count).collect())
+}
+
+/// number of times each monkey inspects one item (starting with `monkey`) over
+/// `rounds` rounds without relief. Items move independently, and only the worry level
+/// modulo the product of the divisors matters, so the item's (monkey, worry) at the
+/// start of a round must eventually repeat. Skip whole cycles once it does.
+fn item_inspections(
+    monkeys: &[Monkey],
+    monkey: usize,
+    worry: Worry,
+    rounds: u64,
+) -> Result<Vec<u64>, Error> {
+    let modulus = common_modulus(monkeys)?;
+    let mut state = (monkey, worry % modulus);
+    let mut seen = HashMap::new(); // round each state was first seen at start of
+    let mut totals = vec![vec![0; monkeys.len()]]; // inspections before each round
+
+    for round in 0..rounds {
+        if let Some(start) = seen.insert(state, round) {
+            // totals repeat from `start`, adding the same amount every cycle
+            let (start, length) = (start as usize, round as usize - start as usize);
+            let cycles = (rounds as usize - start) / length;
+            let remaining = (rounds as usize - start) % length;
+            return (0..monkeys.len())
+                .map(|m| {
+                    let per_cycle = totals[start + length][m] - totals[start][m];
+                    (cycles as u64)
+                        .checked_mul(per_cycle)
+                        .and_then(|n| n.checked_add(totals[start + remaining][m]))
+                        .ok_or(Error::Overflow(format!("monkey {m}'s inspection count")))
+                })
+                .collect();
+        }
+
+        // the item is inspected again this round until it's thrown to a monkey that
+        // has already had its turn
+        let mut counts = totals[totals.len() - 1].clone();
+        let (mut monkey_idx, mut worry) = state;
+        loop {
+            counts[monkey_idx] += 1;
+            worry = monkeys[monkey_idx].operation.apply(worry).ok_or_else(|| {
+                Error::Overflow(format!(
+                    "round {}, monkey {monkey_idx}'s operation",
+                    round + 1
+                ))
+            })? % modulus;
+            let target = monkeys[monkey_idx].target(worry);
+            let same_round = target > monkey_idx;
+            monkey_idx = target;
+            if !same_round {
+                break;
+            }
+        }
+        state = (monkey_idx, worry);
+        totals.push(counts);
+    }
+    Ok(totals.pop().unwrap_or_default())
+}
+
+/// same as `simulate` without relief, following each item on its own
+fn simulate_per_item(monkeys: &[Monkey], rounds: u64) -> Result<Vec<u64>, Error> {
+    let mut counts = vec![0; monkeys.len()];
+    for (monkey_idx, monkey) in monkeys.iter().enumerate() {
+        for worry in &monkey.items {
+            let item_counts = item_inspections(monkeys, monkey_idx, *worry, rounds)?;
+            for (count, item_count) in counts.iter_mut().zip(item_counts) {
+                *count += item_count;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// product of the two highest inspection counts
+fn monkey_business(counts: &[u64]) -> u128 {
+    counts
         .iter()
-        .map(|m| m.inspect_count)
         .sorted()
         .rev()
-        .collect::<Vec<u64>>();
+        .take(2)
+        .map(|c| *c as u128)
+        .product()
+}
 
-    counts[0] * counts[1]
+pub fn part_one(input: &str) -> Result<u128, Error> {
+    let mut monkeys = parse_monkeys(input)?;
+    Ok(monkey_business(&simulate(
+        &mut monkeys,
+        20,
+        Relief::Divide(3),
+    )?))
 }
 
-pub fn part_one(input: &str) -> Option<u64> {
-    Some(run(input, true))
+pub fn part_two(input: &str) -> Result<u128, Error> {
+    let mut monkeys = parse_monkeys(input)?;
+    Ok(monkey_business(&simulate(
+        &mut monkeys,
+        10000,
+        Relief::None,
+    )?))
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    Some(run(input, false))
+/// part two, following each item on its own
+pub fn part_two_per_item(input: &str) -> Result<u128, Error> {
+    let monkeys = parse_monkeys(input)?;
+    Ok(monkey_business(&simulate_per_item(&monkeys, 10000)?))
 }
 
 fn main() {
+    // `cargo solve 11 -- --per-item` solves part two by following each item on its own
+    let per_item = pico_args::Arguments::from_env().contains("--per-item");
     let input = &advent_of_code::read_file("inputs", 11);
     advent_of_code::solve!(1, part_one, input);
-    advent_of_code::solve!(2, part_two, input);
+    if per_item {
+        advent_of_code::solve!(2, part_two_per_item, input);
+    } else {
+        advent_of_code::solve!(2, part_two, input);
+    }
 }
 
 #[cfg(test)]
@@ -162,12 +358,98 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 11);
-        assert_eq!(part_one(&input), Some(10605));
+        assert_eq!(part_one(&input), Ok(10605));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 11);
-        assert_eq!(part_two(&input), Some(2713310158));
+        assert_eq!(part_two(&input), Ok(2713310158));
+    }
+
+    #[test]
+    fn test_part_two_per_item() {
+        let input = advent_of_code::read_file("examples", 11);
+        assert_eq!(part_two_per_item(&input), Ok(2713310158));
+
+        // both engines agree on every count, for any number of rounds
+        for rounds in [1, 20, 1000, 4321] {
+            let mut monkeys = parse_monkeys(&input).unwrap();
+            let per_item = simulate_per_item(&monkeys, rounds);
+            assert_eq!(simulate(&mut monkeys, rounds, Relief::None), per_item);
+        }
+
+        // far more rounds than could be simulated: the example's items all repeat
+        // every 448 or 171 rounds, so counts grow by the same amount over any whole
+        // number of both (once every item has settled into its cycle)
+        let monkeys = parse_monkeys(&input).unwrap();
+        let period = 448 * 171;
+        let growth = |rounds| {
+            let before = simulate_per_item(&monkeys, rounds).unwrap();
+            let after = simulate_per_item(&monkeys, rounds + period).unwrap();
+            after.iter().zip(before).map(|(a, b)| a - b).collect_vec()
+        };
+        assert_eq!(growth(1000000000000), growth(1000));
+    }
+
+    #[test]
+    fn test_parse_monkeys() {
+        let input = advent_of_code::read_file("examples", 11);
+        let monkeys = parse_monkeys(&input).unwrap();
+        assert_eq!(monkeys.len(), 4);
+        assert_eq!(monkeys[0].items, vec![79, 98]);
+        assert_eq!(
+            monkeys[0].operation,
+            Operation::Mul(Operand::Old, Operand::Const(19))
+        );
+        assert_eq!(
+            monkeys[2].operation,
+            Operation::Mul(Operand::Old, Operand::Old)
+        );
+        assert_eq!(monkeys[2].operation.apply(79), Some(6241));
+        assert_eq!(
+            (monkeys[3].divisor, monkeys[3].if_true, monkeys[3].if_false),
+            (17, 0, 1)
+        );
+
+        let err = |from: &str, to: &str| {
+            parse_monkeys(&input.replacen(from, to, 1))
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            err("If false: throw to monkey 3", "If false: throw to monkey 4"),
+            "line 6, column 31: there is no monkey 4 (in \"    If false: throw to monkey 4\")"
+        );
+        assert_eq!(
+            err("new = old * 19", "new = old - 19"),
+            "line 3, column 1: expected operator \"*\" or \"+\" (in \"  Operation: new = old - 19\")"
+        );
+        assert!(err("new = old * 19", "new = old * x").starts_with("line 3, column 26:"));
+        assert!(err("divisible by 23", "divisible by 0").starts_with("line 4, column 1:"));
+    }
+
+    #[test]
+    fn test_relief() {
+        let input = advent_of_code::read_file("examples", 11);
+        let counts = |rounds, relief| simulate(&mut parse_monkeys(&input).unwrap(), rounds, relief);
+        assert_eq!(counts(20, Relief::Divide(3)), Ok(vec![101, 95, 7, 105]));
+        assert_eq!(counts(20, Relief::None), Ok(vec![99, 97, 8, 103]));
+        assert_eq!(counts(1, Relief::Divide(1)), Ok(vec![2, 4, 3, 6]));
+
+        // worry levels kept in full get too big for even a u128 after a while
+        assert_eq!(
+            counts(100, Relief::Divide(1)),
+            Err(Error::Overflow(String::from(
+                "round 31, monkey 0's operation"
+            )))
+        );
+        let square = Operation::Mul(Operand::Old, Operand::Old);
+        assert_eq!(
+            square.apply(u64::MAX as Worry),
+            Some(u64::MAX as Worry * u64::MAX as Worry)
+        );
+        assert_eq!(square.apply(1 << 64), None);
     }
 }
@@ -1,7 +1,28 @@
+use advent_of_code::helpers::{parse_lines, ParseError};
 use itertools::Itertools;
 use pathfinding::prelude::dijkstra;
 use std::collections::HashMap;
 
+/// why the shortest path couldn't be found
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    NoPath, // the target can't be reached from any start
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::NoPath => write!(f, "no path reaches the target"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 struct Index(usize, usize);
 struct Node {
@@ -13,26 +34,49 @@ impl Node {
     }
 }
 
-fn parse_input(input: &str, part2: bool) -> (Vec<Index>, Index, HashMap<Index, Node>) {
-    let labels = input.lines().map(|l| l.chars().collect_vec()).collect_vec();
+/// start positions, target and the steps that can be taken from each position
+type Map = (Vec<Index>, Index, HashMap<Index, Node>);
+
+fn parse_input(input: &str, part2: bool) -> Result<Map, ParseError> {
+    let mut width = None;
+    let labels = parse_lines(input, |line| {
+        if *width.get_or_insert(line.len()) != line.len() {
+            return Err(ParseError::new(line, 1, "expected rows of the same length"));
+        }
+        match line
+            .chars()
+            .position(|c| !matches!(c, 'a'..='z' | 'S' | 'E'))
+        {
+            Some(idx) => Err(ParseError::new(line, idx + 1, "expected a-z, S or E")),
+            None => Ok(line.chars().collect_vec()),
+        }
+    })?;
 
     // find start and target idx
     let mut start = Vec::<Index>::new();
-    let mut target = Index(usize::MAX, usize::MAX);
-    for i in 0..labels.len() {
-        for j in 0..labels[0].len() {
-            if labels[i][j] == 'S' {
+    let mut target = None;
+    for (i, row) in labels.iter().enumerate() {
+        for (j, label) in row.iter().enumerate() {
+            if *label == 'S' {
                 start.push(Index(i, j));
             }
             // if part2 consider all elevation 'a' locations as start candidates
-            if part2 & (labels[i][j] == 'a') {
+            if part2 & (*label == 'a') {
                 start.push(Index(i, j))
             }
-            if labels[i][j] == 'E' {
-                target = Index(i, j);
+            if *label == 'E' {
+                target = Some(Index(i, j));
             }
         }
     }
+    let (Some(target), false) = (target, start.is_empty()) else {
+        return Err(ParseError::new(
+            input.lines().last().unwrap_or(""),
+            1,
+            "expected a start S and a target E",
+        )
+        .offset(labels.len()));
+    };
 
     // determine elevations from labels
     let elevations = labels
@@ -93,22 +137,22 @@ fn parse_input(input: &str, part2: bool) -> (Vec<Index>, Index, HashMap<Index, N
             }
         }
     }
-    (start, target, nodes)
+    Ok((start, target, nodes))
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let (start, target, nodes) = parse_input(input, false);
+pub fn part_one(input: &str) -> Result<u32, Error> {
+    let (start, target, nodes) = parse_input(input, false)?;
     let result = dijkstra(
         &start[0],
         |node| nodes.get(node).unwrap().neighbours(),
         |idx| *idx == target,
     )
-    .unwrap();
-    Some(result.1 as u32)
+    .ok_or(Error::NoPath)?;
+    Ok(result.1 as u32)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let (start_candidates, target, nodes) = parse_input(input, true);
+pub fn part_two(input: &str) -> Result<u32, Error> {
+    let (start_candidates, target, nodes) = parse_input(input, true)?;
     let mut min_path = u32::MAX;
     for start in start_candidates {
         let result = dijkstra(
@@ -123,7 +167,10 @@ pub fn part_two(input: &str) -> Option<u32> {
             }
         }
     }
-    Some(min_path)
+    match min_path {
+        u32::MAX => Err(Error::NoPath),
+        _ => Ok(min_path),
+    }
 }
 
 fn main() {
@@ -139,12 +186,22 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 12);
-        assert_eq!(part_one(&input), Some(31));
+        assert_eq!(part_one(&input), Ok(31));
+
+        assert_eq!(
+            part_one("Sab\nab#").unwrap_err().to_string(),
+            "line 2, column 3: expected a-z, S or E (in \"ab#\")"
+        );
+        assert_eq!(
+            part_one("Sab\nabc").unwrap_err().to_string(),
+            "line 2, column 1: expected a start S and a target E (in \"abc\")"
+        );
+        assert_eq!(part_one("Sbz\nabE"), Err(Error::NoPath));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 12);
-        assert_eq!(part_two(&input), Some(29));
+        assert_eq!(part_two(&input), Ok(29));
     }
 }
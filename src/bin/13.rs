@@ -1,163 +1,158 @@
+use advent_of_code::helpers::{blocks, parse_lines, ParseError};
 use itertools::Itertools;
 use std::cmp::Ordering;
-use std::iter::zip;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
-enum Token {
-    Open,
-    Close,
-    Integer(u32),
-}
-
-#[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
 enum Packet {
     Integer(u32),
     List(Vec<Packet>),
 }
 
-fn parse_tokens(line: &str) -> Vec<Token> {
-    let mut tokens = Vec::<Token>::new();
-    let mut digit_cache: String = "".to_string();
-    for new_char in line.chars() {
-        if new_char.is_ascii_digit() {
-            digit_cache.push(new_char); // keep track of consecutive digits
-        } else if !digit_cache.is_empty() {
-            // convert string of cached digits to u32
-            tokens.push(Token::Integer(digit_cache.parse::<u32>().unwrap()));
-            digit_cache = "".to_string();
-        }
-        if new_char == '[' {
-            tokens.push(Token::Open)
-        } else if new_char == ']' {
-            tokens.push(Token::Close)
+impl Ord for Packet {
+    /// packets are in the right order if left < right
+    fn cmp(&self, other: &Packet) -> Ordering {
+        match (self, other) {
+            (Packet::Integer(l_value), Packet::Integer(r_value)) => l_value.cmp(r_value),
+            // compare items in turn, if all equal the shorter list comes first
+            (Packet::List(l_list), Packet::List(r_list)) => l_list.cmp(r_list),
+            // mixed types: convert the integer to a list containing only that integer
+            (Packet::Integer(l_value), Packet::List(_)) => {
+                Packet::List(vec![Packet::Integer(*l_value)]).cmp(other)
+            }
+            (Packet::List(_), Packet::Integer(r_value)) => {
+                self.cmp(&Packet::List(vec![Packet::Integer(*r_value)]))
+            }
         }
     }
-    tokens
 }
 
-fn parse_packet(tokens: Vec<Token>) -> (Packet, Vec<Token>) {
-    let t = &tokens[0];
-    match t {
-        Token::Open => {
-            parse_list(tokens[1..].to_vec()) // parse the list body
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Packet) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// equality matches the puzzle ordering, so e.g. 2 == [2] == [[2]]
+impl PartialEq for Packet {
+    fn eq(&self, other: &Packet) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Packet {}
+
+/// parse the packet starting at byte `pos` of `line`, leaving `pos` just after it
+fn parse_packet(line: &str, pos: &mut usize) -> Result<Packet, ParseError> {
+    let bytes = line.as_bytes();
+    let err = |pos: usize, message: &str| ParseError::new(line, pos + 1, message);
+    match bytes.get(*pos) {
+        Some(b'[') => {
+            *pos += 1;
+            let mut list = Vec::<Packet>::new();
+            if bytes.get(*pos) == Some(&b']') {
+                // empty list
+                *pos += 1;
+                return Ok(Packet::List(list));
+            }
+            loop {
+                list.push(parse_packet(line, pos)?);
+                match bytes.get(*pos) {
+                    Some(b',') => *pos += 1,
+                    Some(b']') => {
+                        *pos += 1;
+                        return Ok(Packet::List(list));
+                    }
+                    _ => return Err(err(*pos, "expected ',' or ']'")),
+                }
+            }
         }
-        Token::Integer(value) => {
-            (Packet::Integer(*value), tokens[1..].to_vec()) // return int value and remaining tokens
+        Some(b) if b.is_ascii_digit() => {
+            let start = *pos;
+            while bytes.get(*pos).is_some_and(|b| b.is_ascii_digit()) {
+                *pos += 1;
+            }
+            line[start..*pos]
+                .parse::<u32>()
+                .map(Packet::Integer)
+                .map_err(|e| err(start, &format!("invalid integer: {e}")))
         }
-        _ => panic!("close brackets should be parsed in parse_list"),
+        _ => Err(err(*pos, "expected '[' or an integer")),
     }
 }
 
-fn parse_list(mut tokens: Vec<Token>) -> (Packet, Vec<Token>) {
-    let t = &tokens[0];
-    let mut list = Vec::<Packet>::new();
-    match t {
-        Token::Close => (Packet::List(list), tokens[1..].to_vec()), // empty list
-        _ => {
-            while !matches!(tokens[0], Token::Close) {
-                // keep parsing tokens until next closed bracket
-                let (pack, new_tokens) = parse_packet(tokens.to_vec());
-                tokens = new_tokens;
-                list.push(pack);
-            }
-            (Packet::List(list.clone()), tokens[1..].to_vec())
+impl FromStr for Packet {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Packet, ParseError> {
+        let mut pos = 0;
+        let packet = parse_packet(line, &mut pos)?;
+        if pos != line.len() {
+            return Err(ParseError::new(
+                line,
+                pos + 1,
+                "unexpected text after packet",
+            ));
         }
+        Ok(packet)
     }
 }
 
-fn parse_input_p1(input: &str) -> Vec<(Packet, Packet)> {
-    input
-        .split("\n\n") // each group of two packets
-        .map(|pair| {
-            pair.lines() // each packet line as str
-                .map(|l| parse_packet(parse_tokens(l)).0) // str to tokens to Packet
-                .collect_tuple::<(Packet, Packet)>()
-                .unwrap()
-        })
-        .collect_vec()
+impl std::fmt::Display for Packet {
+    /// same format as the puzzle input
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Packet::Integer(value) => write!(f, "{value}"),
+            Packet::List(list) => write!(f, "[{}]", list.iter().join(",")),
+        }
+    }
 }
 
-fn parse_input_p2(input: &str) -> Vec<Packet> {
-    input
-        .lines()
-        .filter(|l| !l.is_empty())
-        .map(|l| parse_packet(parse_tokens(l)).0) // str to tokens to Packet
-        .collect_vec()
+fn parse_input_p1(input: &str) -> Result<Vec<(Packet, Packet)>, ParseError> {
+    blocks(input) // each group of two packets
+        .into_iter()
+        .map(|(offset, pair)| {
+            let packets = parse_lines(pair, Packet::from_str).map_err(|e| e.offset(offset))?;
+            packets.into_iter().collect_tuple().ok_or_else(|| {
+                ParseError::new(pair, 1, "expected a pair of packets").offset(offset + 1)
+            })
+        })
+        .collect()
 }
 
-fn compare_list(l_list: &Vec<Packet>, r_list: &Vec<Packet>) -> Option<bool> {
-    for (left, right) in zip(l_list, r_list) {
-        if let Some(correct) = compare(left, right) {
-            return Some(correct);
-        }
-    }
-    // ran out of items
-    match l_list.cmp(r_list) {
-        Ordering::Less => Some(true), // all equal and left shorter is correct
-        Ordering::Greater => Some(false), // all equal but right shorter is wrong
-        _ => None,                    // all equal and same length is unknown, continue
-    }
+fn parse_input_p2(input: &str) -> Result<Vec<Packet>, ParseError> {
+    let pairs = parse_input_p1(input)?;
+    Ok(pairs.into_iter().flat_map(|(l, r)| [l, r]).collect())
 }
 
-fn compare(left: &Packet, right: &Packet) -> Option<bool> {
-    match left {
-        Packet::Integer(l_value) => match right {
-            Packet::Integer(r_value) => {
-                match l_value.cmp(r_value) {
-                    Ordering::Greater => Some(false), // left larger than right is wrong
-                    Ordering::Less => Some(true),     // left smaller than right is correct
-                    Ordering::Equal => None, // if left and right are equal we don't know yet, continue
-                }
-            }
-            Packet::List(r_list) => {
-                let l_list = &vec![Packet::Integer(*l_value)];
-                compare_list(l_list, r_list)
-            }
-        },
-        Packet::List(l_list) => match right {
-            Packet::Integer(r_value) => {
-                let r_list = &vec![Packet::Integer(*r_value)];
-                compare_list(l_list, r_list)
-            }
-            Packet::List(r_list) => compare_list(l_list, r_list),
-        },
-    }
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let pairs = parse_input_p1(input)?;
+    let sum_idx: usize = pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (left, right))| left < right)
+        .map(|(idx, _)| idx + 1)
+        .sum();
+    Ok(sum_idx as u32)
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let pairs = parse_input_p1(input);
-    let mut sum_idx: usize = 0;
-    for (idx, p) in pairs.iter().enumerate() {
-        match compare(&p.0, &p.1) {
-            Some(true) => {
-                sum_idx += idx + 1;
-            }
-            Some(false) => {
-                continue;
-            }
-            _ => {
-                panic!("undefined comparison");
-            }
-        }
-    }
-    Some(sum_idx as u32)
+/// 1-based position of each divider if the dividers were added to the packets and
+/// everything was sorted, found by counting the packets that come before it
+fn divider_ranks(packets: &[Packet], dividers: &[Packet]) -> Vec<usize> {
+    dividers
+        .iter()
+        .map(|divider| {
+            let before = packets.iter().chain(dividers).filter(|p| *p < divider);
+            before.count() + 1
+        })
+        .collect()
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut packets = parse_input_p2(input);
-    let divider_1 = Packet::List(vec![Packet::List(vec![Packet::Integer(2)])]);
-    let divider_2 = Packet::List(vec![Packet::List(vec![Packet::Integer(6)])]);
-    packets.push(divider_1.clone());
-    packets.push(divider_2.clone());
-    packets.sort_by(|l, r| match compare(l, r) {
-        Some(true) => Ordering::Less,
-        Some(false) => Ordering::Greater,
-        _ => Ordering::Equal,
-    });
-    let div1_idx = packets.iter().position(|pack| *pack == divider_1).unwrap() + 1;
-    let div2_idx = packets.iter().position(|pack| *pack == divider_2).unwrap() + 1;
-
-    Some((div1_idx * div2_idx) as u32)
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let packets = parse_input_p2(input)?;
+    let dividers = ["[[2]]".parse()?, "[[6]]".parse()?];
+    let ranks = divider_ranks(&packets, &dividers);
+    Ok(ranks.iter().product::<usize>() as u32)
 }
 
 fn main() {
@@ -173,12 +168,83 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 13);
-        assert_eq!(part_one(&input), Some(13));
+        assert_eq!(part_one(&input), Ok(13));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 13);
-        assert_eq!(part_two(&input), Some(140));
+        assert_eq!(part_two(&input), Ok(140));
+    }
+
+    #[test]
+    fn test_packet() {
+        let input = advent_of_code::read_file("examples", 13);
+        for line in input.lines().filter(|l| !l.is_empty()) {
+            assert_eq!(line.parse::<Packet>().unwrap().to_string(), line);
+        }
+
+        let packet = |s: &str| s.parse::<Packet>().unwrap();
+        assert!(packet("[[1],[2,3,4]]") < packet("[[1],4]"));
+        assert!(packet("[[[]]]") > packet("[[]]"));
+        assert_eq!(packet("[[2]]"), packet("2"));
+
+        let err = "[1,[2,3]".parse::<Packet>().unwrap_err();
+        assert_eq!(
+            (err.column, err.message.as_str()),
+            (9, "expected ',' or ']'")
+        );
+        assert_eq!("[1]]".parse::<Packet>().unwrap_err().column, 4);
+    }
+
+    #[test]
+    fn test_divider_ranks() {
+        let input = advent_of_code::read_file("examples", 13);
+        let packets = parse_input_p2(&input).unwrap();
+        let dividers = ["[[2]]", "[[6]]", "[]", "[10]"].map(|d| d.parse::<Packet>().unwrap());
+
+        let mut sorted = packets.iter().chain(&dividers).cloned().collect_vec();
+        sorted.sort();
+        let expected = dividers
+            .iter()
+            .map(|d| sorted.iter().position(|p| p == d).unwrap() + 1)
+            .collect_vec();
+        assert_eq!(divider_ranks(&packets, &dividers), expected);
+        // positions 10 and 14 from the puzzle, moved along one by the extra [] divider
+        assert_eq!(expected[..2], [11, 15]);
+    }
+
+    /// random packet from a simple linear congruential generator
+    fn random_packet(seed: &mut u64, depth: u32) -> Packet {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let value = (*seed >> 33) as u32;
+        if (depth == 0) || value.is_multiple_of(3) {
+            Packet::Integer(value % 4)
+        } else {
+            let len = value % 4;
+            Packet::List((0..len).map(|_| random_packet(seed, depth - 1)).collect())
+        }
+    }
+
+    #[test]
+    fn test_total_order() {
+        let mut seed = 13;
+        let packets = (0..60).map(|_| random_packet(&mut seed, 3)).collect_vec();
+        for a in &packets {
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            for b in &packets {
+                // antisymmetric, and equality agrees with the ordering
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a} vs {b}");
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                for c in &packets {
+                    // transitive
+                    if (a <= b) && (b <= c) {
+                        assert!(a <= c, "{a} <= {b} <= {c}");
+                    }
+                }
+            }
+        }
     }
 }
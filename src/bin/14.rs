@@ -1,150 +1,258 @@
-use std::cmp::Ordering;
-use std::collections::HashSet;
-
+use advent_of_code::helpers::{ints_as, parse_lines, ParseError};
 use itertools::Itertools;
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 struct Point {
-    x: u32,
-    y: u32,
+    x: i32,
+    y: i32,
 }
 
-/// convert vec of points (connected by arrows) to HashSet of all points included in that path
-fn expand_path(instruction: &[Point]) -> HashSet<Point> {
-    let mut path = HashSet::<Point>::from_iter(instruction.to_owned());
+const SOURCE: Point = Point { x: 500, y: 0 };
+
+/// convert vec of points (connected by arrows) to vec of all points included in that path
+fn expand_path(instruction: &[Point]) -> Vec<Point> {
+    let mut path = vec![instruction[0]];
     for slice in instruction.windows(2) {
-        let start = slice[0];
-        let end = slice[1];
-        match start.x.cmp(&end.x) {
-            Ordering::Equal => {
-                // horizontal path
-                let ys = vec![start.y, end.y];
-                let ymin = *ys.iter().min().unwrap();
-                let ymax = *ys.iter().max().unwrap();
-                for y in ymin..(ymax + 1) {
-                    path.insert(Point { x: start.x, y });
-                }
-            }
-            _ => {
-                // vertical path
-                let xs = vec![start.x, end.x];
-                let xmin = *xs.iter().min().unwrap();
-                let xmax = *xs.iter().max().unwrap();
-                for x in xmin..(xmax + 1) {
-                    path.insert(Point { x, y: start.y });
-                }
-            }
+        let (start, end) = (slice[0], slice[1]);
+        // one step along the line (horizontal or vertical)
+        let dx = (end.x - start.x).signum();
+        let dy = (end.y - start.y).signum();
+        let mut pos = start;
+        while pos != end {
+            pos = Point {
+                x: pos.x + dx,
+                y: pos.y + dy,
+            };
+            path.push(pos);
         }
     }
     path
 }
 
-/// input -> list of points that are blocked
-fn parse_input(input: &str) -> HashSet<Point> {
-    let instructions = input
-        .lines()
-        .map(|line| {
-            line.split(" -> ")
-                .map(|coords| {
-                    let xy = coords.split(',').collect_tuple::<(&str, &str)>().unwrap();
-                    Point {
-                        x: xy.0.parse::<u32>().unwrap(),
-                        y: xy.1.parse::<u32>().unwrap(),
-                    }
-                })
-                .collect_vec()
-        })
-        .collect_vec();
+/// input -> list of points that are blocked by rock
+fn parse_input(input: &str) -> Result<Vec<Point>, ParseError> {
+    let paths = parse_lines(input, |line| {
+        let values = ints_as::<i32>(line)?;
+        if values.is_empty() || !values.len().is_multiple_of(2) {
+            return Err(ParseError::new(line, 1, "expected \"x,y -> x,y -> ...\""));
+        }
+        let points = values
+            .chunks(2)
+            .map(|xy| Point { x: xy[0], y: xy[1] })
+            .collect_vec();
+        if let Some(p) = points.iter().find(|p| p.y < 0) {
+            return Err(ParseError::new(
+                line,
+                1,
+                format!("rock at y={} is above the top of the cave (y=0)", p.y),
+            ));
+        }
+        if points
+            .windows(2)
+            .any(|p| (p[0].x != p[1].x) && (p[0].y != p[1].y))
+        {
+            return Err(ParseError::new(
+                line,
+                1,
+                "rock paths must be straight lines",
+            ));
+        }
+        Ok(expand_path(&points))
+    })?;
+    Ok(paths.into_iter().flatten().collect())
+}
 
-    let mut blocked = HashSet::<Point>::new();
-    for instr in instructions {
-        blocked.extend(expand_path(&instr));
-    }
-    blocked
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Cell {
+    Air,
+    Rock,
+    Sand,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    Abyss, // sand falling below the lowest rock is lost
+    Floor, // infinite floor two below the lowest rock
 }
 
-fn drop_sand(blocked: &HashSet<Point>, abyss: Point, part2: bool) -> Option<Point> {
-    let mut pos = Point { x: 500, y: 0 }; // current position of sand
-    loop {
-        if !part2 & ((pos.x <= abyss.x) | (pos.x >= abyss.x)) & (pos.y >= abyss.y) {
-            return None; // reached the abyss without settling
-        } else if part2 & (pos.y > abyss.y) {
-            return Some(pos);
-        } else if !blocked.contains(&Point {
-            x: pos.x,
-            y: pos.y + 1,
-        }) {
-            pos.y += 1; // move down
-        } else if !blocked.contains(&Point {
-            x: pos.x - 1,
-            y: pos.y + 1,
-        }) {
-            pos.y += 1; // move down
-            pos.x -= 1; // move left
-        } else if !blocked.contains(&Point {
-            x: pos.x + 1,
-            y: pos.y + 1,
-        }) {
-            pos.y += 1; // move down
-            pos.x += 1; // move right
+struct Cave {
+    cells: Vec<Cell>, // row by row, covering every point sand could reach
+    x_min: i32,
+    width: i32,
+    y_max: i32, // lowest rock
+    source: Point,
+    mode: Mode,
+    path: Vec<Point>, // route taken by the last grain, the next one follows it as far as possible
+    settled: u32,
+}
+impl Cave {
+    fn new(rocks: &[Point], source: Point, mode: Mode) -> Cave {
+        let y_max = rocks.iter().map(|p| p.y).max().unwrap_or(0).max(source.y);
+        // sand spreads at most one column sideways per row it falls
+        let spread = y_max + 2 - source.y;
+        let x_min = rocks
+            .iter()
+            .map(|p| p.x)
+            .min()
+            .unwrap_or(source.x)
+            .min(source.x - spread)
+            - 1;
+        let x_max = rocks
+            .iter()
+            .map(|p| p.x)
+            .max()
+            .unwrap_or(source.x)
+            .max(source.x + spread)
+            + 1;
+        let width = x_max - x_min + 1;
+        let mut cave = Cave {
+            cells: vec![Cell::Air; (width * (y_max + 2)) as usize],
+            x_min,
+            width,
+            y_max,
+            source,
+            mode,
+            path: vec![source],
+            settled: 0,
+        };
+        for rock in rocks {
+            cave.set(*rock, Cell::Rock);
+        }
+        cave
+    }
+
+    fn get(&self, p: Point) -> Cell {
+        if (p.x < self.x_min) || (p.x >= self.x_min + self.width) || (p.y < 0) {
+            Cell::Air
+        } else if p.y >= self.y_max + 2 {
+            // only reachable in floor mode
+            Cell::Rock
         } else {
-            return Some(pos); // nowhere to move, settled
+            self.cells[(p.y * self.width + p.x - self.x_min) as usize]
         }
     }
-}
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut blocked = parse_input(input);
-    let abyss = Point {
-        x: blocked.iter().min_by_key(|p| p.x).unwrap().x,
-        y: blocked.iter().max_by_key(|p| p.y).unwrap().y,
-    };
-    let mut n_sand = 0;
-    loop {
-        let settled = drop_sand(&blocked, abyss, false);
-        match settled {
-            Some(point) => {
-                n_sand += 1;
-                blocked.insert(point);
+    fn set(&mut self, p: Point, cell: Cell) {
+        self.cells[(p.y * self.width + p.x - self.x_min) as usize] = cell;
+    }
+
+    /// drop one grain of sand, returning where it settled. None if it fell into the
+    /// abyss or the source is blocked.
+    fn drop_grain(&mut self) -> Option<Point> {
+        loop {
+            let pos = *self.path.last()?;
+            if self.get(pos) != Cell::Air {
+                return None; // the source is rock or buried in sand
             }
-            _ => {
-                break;
+            if (self.mode == Mode::Abyss) && (pos.y >= self.y_max) {
+                return None; // below all rocks, will fall forever
+            }
+            // try moving down, then down-left, then down-right
+            let next = [0, -1, 1]
+                .map(|dx| Point {
+                    x: pos.x + dx,
+                    y: pos.y + 1,
+                })
+                .into_iter()
+                .find(|p| self.get(*p) == Cell::Air);
+            match next {
+                Some(p) => self.path.push(p),
+                None => {
+                    // nowhere to move, settled. The next grain takes the same route
+                    // up to the point before this one.
+                    self.set(pos, Cell::Sand);
+                    self.path.pop();
+                    self.settled += 1;
+                    return Some(pos);
+                }
             }
         }
     }
-    Some(n_sand)
-}
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut blocked = parse_input(input);
-    let abyss = Point {
-        x: blocked.iter().min_by_key(|p| p.x).unwrap().x,
-        y: blocked.iter().max_by_key(|p| p.y).unwrap().y,
-    };
-    let mut n_sand = 0;
-    loop {
-        let settled = drop_sand(&blocked, abyss, true);
-        match settled {
-            Some(point) => {
-                n_sand += 1;
-                if point == (Point { x: 500, y: 0 }) {
-                    break;
-                } else {
-                    blocked.insert(point);
-                }
-            }
-            _ => {
-                break;
-            }
+    /// drop sand until no more settles, returning the total amount of settled sand
+    fn fill(&mut self) -> u32 {
+        while self.drop_grain().is_some() {}
+        self.settled
+    }
+
+    /// amount of sand that settles with a floor, without simulating grains: sand ends
+    /// up in every cell it can reach from the source, which are the open cells below
+    /// (or diagonally below) a reachable cell in the row above
+    fn flood_fill(&self) -> u32 {
+        let mut row = vec![false; self.width as usize];
+        row[(self.source.x - self.x_min) as usize] = self.get(self.source) == Cell::Air;
+        let mut total = row.iter().filter(|r| **r).count();
+        for y in (self.source.y + 1)..(self.y_max + 2) {
+            row = (0..self.width)
+                .map(|idx| {
+                    let open = self.get(Point {
+                        x: idx + self.x_min,
+                        y,
+                    }) == Cell::Air;
+                    let above = (idx - 1..=idx + 1)
+                        .filter(|i| (*i >= 0) && (*i < self.width))
+                        .any(|i| row[i as usize]);
+                    open && above
+                })
+                .collect();
+            total += row.iter().filter(|r| **r).count();
         }
+        total as u32
+    }
+}
+
+impl std::fmt::Display for Cave {
+    /// draw the cave like in the puzzle text: rock #, sand o, source +
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let filled = (0..self.cells.len() as i32)
+            .filter(|idx| self.cells[*idx as usize] != Cell::Air)
+            .map(|idx| idx % self.width + self.x_min)
+            .chain([self.source.x]);
+        let (x_min, x_max) = filled.minmax().into_option().unwrap();
+        let y_max = match self.mode {
+            Mode::Abyss => self.y_max,
+            Mode::Floor => self.y_max + 2,
+        };
+        let mut rows = (0..=y_max).map(|y| {
+            (x_min..=x_max)
+                .map(|x| match self.get(Point { x, y }) {
+                    Cell::Air if (Point { x, y }) == self.source => '+',
+                    Cell::Air => '.',
+                    Cell::Rock => '#',
+                    Cell::Sand => 'o',
+                })
+                .collect::<String>()
+        });
+        write!(f, "{}", rows.join("\n"))
     }
-    Some(n_sand)
+}
+
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let rocks = parse_input(input)?;
+    Ok(Cave::new(&rocks, SOURCE, Mode::Abyss).fill())
+}
+
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let rocks = parse_input(input)?;
+    Ok(Cave::new(&rocks, SOURCE, Mode::Floor).fill())
+}
+
+/// part two without simulating each grain
+pub fn part_two_flood_fill(input: &str) -> Result<u32, ParseError> {
+    let rocks = parse_input(input)?;
+    Ok(Cave::new(&rocks, SOURCE, Mode::Floor).flood_fill())
 }
 
 fn main() {
+    // `cargo solve 14 -- --flood-fill` solves part two without simulating the sand
+    let flood_fill = pico_args::Arguments::from_env().contains("--flood-fill");
     let input = &advent_of_code::read_file("inputs", 14);
     advent_of_code::solve!(1, part_one, input);
-    advent_of_code::solve!(2, part_two, input);
+    if flood_fill {
+        advent_of_code::solve!(2, part_two_flood_fill, input);
+    } else {
+        advent_of_code::solve!(2, part_two, input);
+    }
 }
 
 #[cfg(test)]
@@ -154,12 +262,78 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 14);
-        assert_eq!(part_one(&input), Some(24));
+        assert_eq!(part_one(&input), Ok(24));
+
+        assert_eq!(
+            part_one("498,-2 -> 498,3").unwrap_err().to_string(),
+            "line 1, column 1: rock at y=-2 is above the top of the cave (y=0) (in \"498,-2 -> 498,3\")"
+        );
+        // rock covering the source
+        assert_eq!(part_one("495,0 -> 505,0\n490,5 -> 510,5"), Ok(0));
+        assert_eq!(part_two("500,0 -> 500,0"), Ok(0));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 14);
-        assert_eq!(part_two(&input), Some(93));
+        assert_eq!(part_two(&input), Ok(93));
+    }
+
+    #[test]
+    fn test_part_two_flood_fill() {
+        let input = advent_of_code::read_file("examples", 14);
+        assert_eq!(part_two_flood_fill(&input), Ok(93));
+
+        // cross-check against the simulation, including shapes that leave gaps under
+        // overhangs and a source that isn't at the top
+        let caves = [
+            "490,5 -> 510,5\n495,8 -> 505,8 -> 505,6",
+            "500,3 -> 500,3\n497,6 -> 503,6",
+            "480,12 -> 520,12\n499,2 -> 501,2 -> 501,4\n490,9 -> 490,11 -> 494,11",
+        ];
+        for cave in caves {
+            let rocks = parse_input(cave).unwrap();
+            for source in [SOURCE, Point { x: 498, y: 1 }] {
+                let mut simulated = Cave::new(&rocks, source, Mode::Floor);
+                let flood_filled = Cave::new(&rocks, source, Mode::Floor).flood_fill();
+                assert_eq!(simulated.fill(), flood_filled, "{cave}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let input = advent_of_code::read_file("examples", 14);
+        let mut cave = Cave::new(&parse_input(&input).unwrap(), SOURCE, Mode::Abyss);
+        for _ in 0..5 {
+            cave.drop_grain();
+        }
+        assert_eq!(
+            cave.to_string(),
+            "......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+......o.#.
+....oooo#.
+#########."
+        );
+        cave.fill();
+        assert_eq!(
+            cave.to_string(),
+            "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."
+        );
     }
 }
@@ -1,7 +1,8 @@
 use std::{cmp::Ordering, collections::HashSet};
 
+use advent_of_code::helpers::{ints_as, parse_lines, ParseError};
+use geometry::{Rect, Region};
 use itertools::Itertools;
-use regex::Regex;
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
 struct Point {
@@ -29,8 +30,8 @@ impl Range {
             (*self, None)
         } else {
             // extend range
-            let min = *vec![self.min, other.min].iter().min().unwrap();
-            let max = *vec![self.max, other.max].iter().max().unwrap();
+            let min = *[self.min, other.min].iter().min().unwrap();
+            let max = *[self.max, other.max].iter().max().unwrap();
             (Range { min, max }, None)
         }
     }
@@ -40,18 +41,225 @@ impl Range {
     fn len(&self) -> u32 {
         (self.max - self.min + 1) as u32
     }
-    fn clip(&self, extent: Range) -> Range {
-        let new_min = match self.min.cmp(&extent.min) {
-            Ordering::Less => extent.min,
-            _ => self.min,
-        };
-        let new_max = match self.max.cmp(&extent.max) {
-            Ordering::Greater => extent.max,
-            _ => self.max,
-        };
-        Range {
-            min: new_min,
-            max: new_max,
+}
+
+/// coverage geometry in rotated coordinates u = x + y, v = x - y, where the diamond of
+/// cells within a manhattan distance of a sensor becomes an axis-aligned square
+mod geometry {
+    /// inclusive ranges of u and v
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Rect {
+        pub u_min: i64,
+        pub u_max: i64,
+        pub v_min: i64,
+        pub v_max: i64,
+    }
+    impl Rect {
+        /// cells within manhattan distance `radius` of (x, y)
+        pub fn diamond(x: i64, y: i64, radius: i64) -> Rect {
+            Rect {
+                u_min: x + y - radius,
+                u_max: x + y + radius,
+                v_min: x - y - radius,
+                v_max: x - y + radius,
+            }
+        }
+
+        fn overlaps(&self, other: &Rect) -> bool {
+            (self.u_min <= other.u_max)
+                && (other.u_min <= self.u_max)
+                && (self.v_min <= other.v_max)
+                && (other.v_min <= self.v_max)
+        }
+
+        /// the parts of self not covered by other, as up to 4 non-overlapping rects
+        pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+            if !self.overlaps(other) {
+                return vec![*self];
+            }
+            let mut pieces = Vec::new();
+            // full height strips left and right of other
+            if self.u_min < other.u_min {
+                pieces.push(Rect {
+                    u_max: other.u_min - 1,
+                    ..*self
+                });
+            }
+            if self.u_max > other.u_max {
+                pieces.push(Rect {
+                    u_min: other.u_max + 1,
+                    ..*self
+                });
+            }
+            // strips above and below other, between the left and right strips
+            let u_min = self.u_min.max(other.u_min);
+            let u_max = self.u_max.min(other.u_max);
+            if self.v_min < other.v_min {
+                pieces.push(Rect {
+                    u_min,
+                    u_max,
+                    v_min: self.v_min,
+                    v_max: other.v_min - 1,
+                });
+            }
+            if self.v_max > other.v_max {
+                pieces.push(Rect {
+                    u_min,
+                    u_max,
+                    v_min: other.v_max + 1,
+                    v_max: self.v_max,
+                });
+            }
+            pieces
+        }
+    }
+
+    /// inclusive ranges of x and y
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Region {
+        pub x_min: i64,
+        pub x_max: i64,
+        pub y_min: i64,
+        pub y_max: i64,
+    }
+    impl Region {
+        /// smallest rect containing the region
+        fn bounds(&self) -> Rect {
+            Rect {
+                u_min: self.x_min + self.y_min,
+                u_max: self.x_max + self.y_max,
+                v_min: self.x_min - self.y_max,
+                v_max: self.x_max - self.y_min,
+            }
+        }
+
+        pub fn contains(&self, x: i64, y: i64) -> bool {
+            (self.x_min..=self.x_max).contains(&x) && (self.y_min..=self.y_max).contains(&y)
+        }
+
+        /// number of cells in the region with x + y <= u and x - y <= v
+        fn count_below(&self, u: i64, v: i64) -> i64 {
+            // for each x the cells run from y = max(y_min, x - v) to min(y_max, u - x).
+            // Split x where either bound switches formula, within each part the count
+            // changes linearly with x (and never increases).
+            let mut starts = [
+                self.x_min,
+                (u - self.y_max + 1).clamp(self.x_min, self.x_max + 1),
+                (v + self.y_min + 1).clamp(self.x_min, self.x_max + 1),
+                self.x_max + 1,
+            ];
+            starts.sort();
+            let count_at = |x: i64| self.y_max.min(u - x) - self.y_min.max(x - v) + 1;
+
+            let mut total = 0;
+            for part in starts.windows(2) {
+                let (start, len) = (part[0], part[1] - part[0]);
+                let first = count_at(start);
+                if (len == 0) || (first <= 0) {
+                    continue;
+                }
+                let slope = -((start > u - self.y_max) as i64) - ((start > v + self.y_min) as i64);
+                // only add the cells while the count is positive
+                let n = if slope == 0 {
+                    len
+                } else {
+                    len.min((first - slope - 1) / -slope)
+                };
+                total += n * first + slope * n * (n - 1) / 2;
+            }
+            total
+        }
+
+        /// number of cells of the region inside `rect`
+        fn count_in(&self, rect: &Rect) -> i64 {
+            self.count_below(rect.u_max, rect.v_max)
+                - self.count_below(rect.u_min - 1, rect.v_max)
+                - self.count_below(rect.u_max, rect.v_min - 1)
+                + self.count_below(rect.u_min - 1, rect.v_min - 1)
+        }
+
+        /// cells of the region inside `rect`. Only visits values of u where the rect
+        /// and the region overlap, so is fast when the result is small.
+        fn cells_in(&self, rect: &Rect) -> Vec<(i64, i64)> {
+            // for a given u, the region covers 2*x_min - u <= v <= 2*x_max - u
+            // and u - 2*y_max <= v <= u - 2*y_min
+            let u_min = [
+                rect.u_min,
+                rect.v_min + 2 * self.y_min,
+                2 * self.x_min - rect.v_max,
+                self.x_min + self.y_min,
+            ];
+            let u_max = [
+                rect.u_max,
+                2 * self.x_max - rect.v_min,
+                rect.v_max + 2 * self.y_max,
+                self.x_max + self.y_max,
+            ];
+            let mut cells = Vec::new();
+            for u in *u_min.iter().max().unwrap()..=*u_max.iter().min().unwrap() {
+                let v_min = rect.v_min.max(2 * self.x_min - u).max(u - 2 * self.y_max);
+                let v_max = rect.v_max.min(2 * self.x_max - u).min(u - 2 * self.y_min);
+                // u and v must both be odd or both be even to be a whole cell
+                let v_start = v_min + (u - v_min).rem_euclid(2);
+                for v in (v_start..=v_max).step_by(2) {
+                    cells.push(((u + v) / 2, (u - v) / 2));
+                }
+            }
+            cells
+        }
+    }
+
+    /// parts of the region's bounding rect not covered by any of the rects
+    fn remaining(covered: &[Rect], region: &Region) -> Vec<Rect> {
+        let mut remaining = vec![region.bounds()];
+        for rect in covered {
+            remaining = remaining.iter().flat_map(|r| r.subtract(rect)).collect();
+        }
+        remaining
+    }
+
+    /// number of cells in the region not covered by any of the rects
+    pub fn count_uncovered(covered: &[Rect], region: &Region) -> u64 {
+        remaining(covered, region)
+            .iter()
+            .map(|r| region.count_in(r))
+            .sum::<i64>() as u64
+    }
+
+    /// number of cells in the region covered by at least one of the rects
+    pub fn count_covered(covered: &[Rect], region: &Region) -> u64 {
+        region.count_below(i64::MAX / 4, i64::MAX / 4) as u64 - count_uncovered(covered, region)
+    }
+
+    /// every cell in the region not covered by any of the rects, sorted by (x, y)
+    pub fn uncovered(covered: &[Rect], region: &Region) -> Vec<(i64, i64)> {
+        let mut cells = remaining(covered, region)
+            .iter()
+            .flat_map(|r| region.cells_in(r))
+            .collect::<Vec<_>>();
+        cells.sort();
+        cells
+    }
+}
+
+/// why the distress beacon couldn't be found
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    Candidates(u64), // number of cells the beacon could be in, if not exactly one
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::Candidates(n) => {
+                write!(f, "expected exactly one place for the beacon, found {n}")
+            }
         }
     }
 }
@@ -79,34 +287,33 @@ impl Reading {
             })
         }
     }
+    fn coverage(&self) -> Rect {
+        Rect::diamond(
+            self.sensor.x as i64,
+            self.sensor.y as i64,
+            self.radius() as i64,
+        )
+    }
 }
 
-fn parse_input(input: &str) -> Vec<Reading> {
-    let re = Regex::new(r"x=(-?\d+), y=(-?\d+)").unwrap();
-
-    let pairs = input
-        .lines()
-        .map(|line| {
-            line.split(": ")
-                .map(|part| {
-                    let matches = &re.captures_iter(part).collect_vec()[0];
-                    Point {
-                        x: matches[1].parse::<i32>().unwrap(),
-                        y: matches[2].parse::<i32>().unwrap(),
-                    }
-                })
-                .collect_tuple::<(Point, Point)>()
-                .unwrap()
-        })
-        .collect_vec();
+/// cells in the region that no sensor covers, i.e. where an unknown beacon could be
+fn uncovered_cells(readings: &[Reading], region: &Region) -> Vec<(i64, i64)> {
+    let covered = readings.iter().map(|r| r.coverage()).collect_vec();
+    geometry::uncovered(&covered, region)
+}
 
-    pairs
-        .iter()
-        .map(|(sensor, beacon)| Reading {
-            sensor: *sensor,
-            beacon: *beacon,
-        })
-        .collect_vec()
+fn parse_input(input: &str) -> Result<Vec<Reading>, ParseError> {
+    parse_lines(input, |line| match ints_as::<i32>(line)?[..] {
+        [sx, sy, bx, by] => Ok(Reading {
+            sensor: Point { x: sx, y: sy },
+            beacon: Point { x: bx, y: by },
+        }),
+        _ => Err(ParseError::new(
+            line,
+            1,
+            "expected sensor and beacon coordinates",
+        )),
+    })
 }
 
 fn merge_ranges(mut ranges: Vec<Option<Range>>) -> Vec<Option<Range>> {
@@ -134,6 +341,9 @@ fn get_row_ranges(readings: &[Reading], y: i32) -> Vec<Range> {
         .map(|r| r.range(y))
         .filter(|r| r.is_some()) // remove empty ranges
         .collect_vec();
+    if ranges.is_empty() {
+        return Vec::new();
+    }
 
     // combine overlapping ranges
     let mut n_ranges = 0;
@@ -144,14 +354,9 @@ fn get_row_ranges(readings: &[Reading], y: i32) -> Vec<Range> {
     ranges.iter().map(|r| r.unwrap()).collect_vec()
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let readings = parse_input(input);
-    let y = match readings.len().cmp(&14) {
-        // decide whether to use y test or y actual
-        Ordering::Equal => 10, // test input
-        _ => 2000000,          // actual input
-    };
-    let ranges = get_row_ranges(&readings, y);
+/// number of cells on row y where a beacon cannot be present
+fn row_excluded(readings: &[Reading], y: i32) -> u32 {
+    let ranges = get_row_ranges(readings, y);
 
     // check for (unique) beacons at the observed coordinates
     let beacons = readings
@@ -171,51 +376,82 @@ pub fn part_one(input: &str) -> Option<u32> {
 
     // count excluded cells
     let range_excluded = ranges.iter().fold(0, |acc, r| acc + r.len()); // no. cells in all ranges
-    Some(range_excluded - seen_beacons)
+    range_excluded - seen_beacons
+}
+
+/// number of cells in the region where a beacon cannot be present, i.e. covered by a
+/// sensor and not one of the known beacons
+fn region_excluded(readings: &[Reading], region: &Region) -> u64 {
+    let covered = readings.iter().map(|r| r.coverage()).collect_vec();
+    // beacons are always covered by their own sensor
+    let beacons = readings
+        .iter()
+        .map(|r| r.beacon)
+        .filter(|b| region.contains(b.x as i64, b.y as i64))
+        .collect::<HashSet<Point>>();
+    geometry::count_covered(&covered, region) - beacons.len() as u64
 }
 
-pub fn part_two(input: &str) -> Option<u64> {
-    let readings = parse_input(input);
-    let extent = match readings.len().cmp(&14) {
-        Ordering::Equal => Range { min: 0, max: 20 }, // test input
-        _ => Range {
-            min: 0,
-            max: 4000000,
-        }, // actual input
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let readings = parse_input(input)?;
+    let y = match readings.len().cmp(&14) {
+        // decide whether to use y test or y actual
+        Ordering::Equal => 10, // test input
+        _ => 2000000,          // actual input
     };
-    for y in extent.min..(extent.max + 1) {
-        // get seen cells (ranges) for this row and clip them to the extent
-        let mut ranges = get_row_ranges(&readings, y)
-            .iter()
-            .map(|r| Some(r.clip(extent)))
-            .collect_vec();
+    Ok(row_excluded(&readings, y))
+}
 
-        // combine overlapping ranges
-        let mut n_ranges = 0;
-        while n_ranges != ranges.len() {
-            n_ranges = ranges.len();
-            ranges = merge_ranges(ranges);
-        }
-        if ranges.len() == 2 {
-            // found an unseen spot (2 ranges with a gap between)
-            let left = ranges[0].unwrap();
-            let right = ranges[1].unwrap();
-            let coord = match right.min.cmp(&left.max) {
-                Ordering::Greater => Point { x: left.max + 1, y },
-                _ => Point {
-                    x: right.max + 1,
-                    y,
-                },
-            };
-            return Some(((coord.x as u64) * 4000000) + (coord.y as u64));
+/// part one using the area computation on a one row region
+pub fn part_one_area(input: &str) -> Result<u64, ParseError> {
+    let readings = parse_input(input)?;
+    let y = match readings.len().cmp(&14) {
+        Ordering::Equal => 10, // test input
+        _ => 2000000,          // actual input
+    };
+    let x_min = readings.iter().map(|r| r.sensor.x - r.radius()).min();
+    let x_max = readings.iter().map(|r| r.sensor.x + r.radius()).max();
+    let region = Region {
+        x_min: x_min.unwrap_or(0) as i64,
+        x_max: x_max.unwrap_or(0) as i64,
+        y_min: y as i64,
+        y_max: y as i64,
+    };
+    Ok(region_excluded(&readings, &region))
+}
+
+pub fn part_two(input: &str) -> Result<u64, Error> {
+    let readings = parse_input(input)?;
+    let max = match readings.len().cmp(&14) {
+        Ordering::Equal => 20, // test input
+        _ => 4000000,          // actual input
+    };
+    let region = Region {
+        x_min: 0,
+        x_max: max,
+        y_min: 0,
+        y_max: max,
+    };
+    // count first, listing the cells is only cheap if there are few of them
+    let covered = readings.iter().map(|r| r.coverage()).collect_vec();
+    match geometry::count_uncovered(&covered, &region) {
+        1 => {
+            let (x, y) = uncovered_cells(&readings, &region)[0];
+            Ok((x * 4000000 + y) as u64)
         }
+        n => Err(Error::Candidates(n)),
     }
-    panic!("Didn't find space for beacon!")
 }
 
 fn main() {
+    // `cargo solve 15 -- --area` solves part one with the area computation
+    let area = pico_args::Arguments::from_env().contains("--area");
     let input = &advent_of_code::read_file("inputs", 15);
-    advent_of_code::solve!(1, part_one, input);
+    if area {
+        advent_of_code::solve!(1, part_one_area, input);
+    } else {
+        advent_of_code::solve!(1, part_one, input);
+    }
     advent_of_code::solve!(2, part_two, input);
 }
 
@@ -226,12 +462,101 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 15);
-        assert_eq!(part_one(&input), Some(26));
+        assert_eq!(part_one(&input), Ok(26));
+    }
+
+    #[test]
+    fn test_part_one_area() {
+        let input = advent_of_code::read_file("examples", 15);
+        assert_eq!(part_one_area(&input), Ok(26));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 15);
-        assert_eq!(part_two(&input), Some(56000011));
+        assert_eq!(part_two(&input), Ok(56000011));
+
+        // a single small sensor leaves almost the whole search area free
+        let small = "Sensor at x=2, y=18: closest beacon is at x=3, y=18";
+        assert_eq!(
+            part_two(small),
+            Err(Error::Candidates(4000001 * 4000001 - 5))
+        );
+        // and one covering everything leaves nowhere
+        let big = "Sensor at x=0, y=0: closest beacon is at x=8000000, y=0";
+        assert_eq!(part_two(big), Err(Error::Candidates(0)));
+
+        let err = part_two("Sensor at x=2, y=18: closest beacon is at x=4000000000, y=15");
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "line 1, column 45: 4000000000 is out of range for i32 (in \"Sensor at x=2, y=18: closest beacon is at x=4000000000, y=15\")"
+        );
+    }
+
+    #[test]
+    fn test_uncovered_cells() {
+        let input = advent_of_code::read_file("examples", 15);
+        let readings = parse_input(&input).unwrap();
+        let region = |x_min, x_max, y_min, y_max| Region {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        };
+        assert_eq!(
+            uncovered_cells(&readings, &region(0, 20, 0, 20)),
+            vec![(14, 11)]
+        );
+
+        // check a bigger area cell by cell
+        let region = region(-8, 30, -5, 25);
+        let expected = (region.x_min..=region.x_max)
+            .cartesian_product(region.y_min..=region.y_max)
+            .filter(|(x, y)| {
+                let p = Point {
+                    x: *x as i32,
+                    y: *y as i32,
+                };
+                readings.iter().all(|r| r.sensor.manhatten(&p) > r.radius())
+            })
+            .collect_vec();
+        assert_eq!(uncovered_cells(&readings, &region), expected);
+    }
+
+    #[test]
+    fn test_region_excluded() {
+        let input = advent_of_code::read_file("examples", 15);
+        let readings = parse_input(&input).unwrap();
+        let region = Region {
+            x_min: -20,
+            x_max: 40,
+            y_min: -15,
+            y_max: 35,
+        };
+
+        // agrees with the row by row count for every row (sensors cover less than the
+        // region's width), and for all rows at once
+        let mut total = 0;
+        for y in region.y_min..=region.y_max {
+            let row = Region {
+                y_min: y,
+                y_max: y,
+                ..region
+            };
+            let expected = row_excluded(&readings, y as i32) as u64;
+            assert_eq!(region_excluded(&readings, &row), expected, "row {y}");
+            total += expected;
+        }
+        assert_eq!(region_excluded(&readings, &region), total);
+
+        // covered cells + uncovered cells = all cells
+        let small = Region {
+            x_min: 0,
+            x_max: 20,
+            y_min: 0,
+            y_max: 20,
+        };
+        let covered = readings.iter().map(|r| r.coverage()).collect_vec();
+        assert_eq!(geometry::count_covered(&covered, &small), 21 * 21 - 1);
     }
 }
@@ -1,208 +1,470 @@
+use advent_of_code::helpers::{parse_lines, ParseError, Template};
 use itertools::Itertools;
 use pathfinding::prelude::dijkstra;
-use regex::Regex;
 use std::collections::HashMap;
 
+/// why an answer couldn't be worked out
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    NoValve(String), // the valve to start from isn't in the input
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::NoValve(name) => write!(f, "no valve called {name}"),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Valve {
     flow_rate: u32,
     tunnels: Vec<String>, // other valves this valve connects to
 }
 
-fn parse_valves(input: &str) -> HashMap<String, Valve> {
-    let re =
-        Regex::new(r"Valve (\w\w) has flow rate=(\d+); tunnels? leads? to valves? (.*)").unwrap();
-    input
-        .lines()
-        .map(|line| {
-            let matches = &re.captures_iter(line).collect_vec()[0];
-            let tunnel_name = matches[1].to_string();
-            let flow_rate = matches[2].parse::<u32>().unwrap();
-            let tunnels = matches[3]
-                .split(", ")
-                .map(|label| label.to_string())
-                .collect_vec();
-            (tunnel_name, Valve { flow_rate, tunnels })
-        })
-        .collect()
+fn parse_valves(input: &str) -> Result<HashMap<String, Valve>, ParseError> {
+    let one = Template::new("Valve {} has flow rate={}; tunnel leads to valve {}");
+    let many = Template::new("Valve {} has flow rate={}; tunnels lead to valves {}");
+    let valves = parse_lines(input, |line| {
+        let template = if line.contains("; tunnel leads") {
+            &one
+        } else {
+            &many
+        };
+        let matches = template.captures(line)?;
+        let tunnels = matches
+            .str(2)
+            .split(", ")
+            .map(|label| label.to_string())
+            .collect_vec();
+        Ok((
+            matches.str(0).to_string(),
+            Valve {
+                flow_rate: matches.get(1)?,
+                tunnels,
+            },
+        ))
+    })?;
+    Ok(valves.into_iter().collect())
 }
 
-/// get path lengths between all the non-zero nodes (+ the start node)
-fn path_lengths(valves: &HashMap<String, Valve>) -> (Vec<String>, Vec<Vec<u32>>) {
-    // only compute paths for the start valve (AA) and valves with non-zero flow rate
+/// get path lengths between all the non-zero nodes (+ the start node), None if there's
+/// no valve called `start`
+fn path_lengths(
+    valves: &HashMap<String, Valve>,
+    start: &str,
+) -> Option<(Vec<String>, Vec<Vec<u32>>)> {
+    if !valves.contains_key(start) {
+        return None;
+    }
+    // only compute paths for the start valve and valves with non-zero flow rate
     let good_valves = valves
         .keys()
-        .filter(|k| (k.to_string() == *"AA") | (valves.get(&k.to_string()).unwrap().flow_rate > 0))
+        .filter(|k| (k.as_str() == start) | (valves.get(*k).unwrap().flow_rate > 0))
         .map(|s| s.to_string())
+        .sorted()
         .collect_vec();
 
     // distance from each interesting valve to each other interesting valve
     let mut distances = vec![vec![u32::MAX; good_valves.len()]; good_valves.len()];
     for (idx_start, start) in good_valves.iter().enumerate() {
         for (idx_end, end) in good_valves.iter().enumerate() {
-            let result: (Vec<String>, usize) = dijkstra(
+            if let Some(result) = dijkstra(
                 start,
                 |v| {
                     valves
                         .get(v)
-                        .unwrap()
-                        .tunnels
+                        .map_or(&[] as &[String], |valve| &valve.tunnels)
                         .iter()
                         .map(|tunnel| (tunnel.to_string(), 1)) // add cost of 1 minute to get to neighbouring valves
                         .collect::<Vec<(String, usize)>>()
                 },
                 |v| *v == *end,
-            )
-            .unwrap();
-            distances[idx_start][idx_end] = result.1 as u32;
+            ) {
+                distances[idx_start][idx_end] = result.1 as u32;
+            }
         }
     }
-    (good_valves, distances)
-}
-
-/// recursively compute valve opening order with the best total pressure
-fn run(
-    flows: &Vec<u32>,          // pressure of each valve (when open)
-    distances: &Vec<Vec<u32>>, // distance from each valve to each other valve
-    time: u32,                 // current time
-    end: u32,                  // end time
-    open_valves: Vec<usize>,   // valves that have been turned on so far
-    current_pressure: u32, // if no more valves were turned on, total pressure achieved at end time
-    mut best_pressure: u32, // best total pressure achieved so far
-) -> u32 {
-    // candidate valve to visit next
-    for valve in 0..flows.len() {
-        // have we already opened this valve?
-        if !open_valves.contains(&valve) {
+    Some((good_valves, distances))
+}
+
+/// depth-first search over valve opening orders, with states keyed by
+/// (position, time left, opened valves bitmask)
+struct Search<'a> {
+    flows: &'a [u32],                        // pressure of each valve (when open)
+    distances: &'a [Vec<u32>],               // distance from each valve to each other valve
+    time: u32,                               // minutes available
+    best: Vec<u32>,                          // best total pressure for each set of opened valves
+    routes: Vec<Vec<(usize, u32)>>,          // (valve, minute opened) giving each of those totals
+    route: Vec<(usize, u32)>,                // valves opened on the way to the current state
+    seen: HashMap<(usize, u32, usize), u32>, // best pressure so far in each state
+}
+impl Search<'_> {
+    fn visit(&mut self, position: usize, time_left: u32, opened: usize, pressure: u32) {
+        // skip states we've already reached with at least as much pressure
+        match self.seen.get(&(position, time_left, opened)) {
+            Some(seen_pressure) if *seen_pressure >= pressure => return,
+            _ => self.seen.insert((position, time_left, opened), pressure),
+        };
+        if pressure > self.best[opened] {
+            self.best[opened] = pressure;
+            self.routes[opened] = self.route.clone();
+        }
+
+        // candidate valve to visit next
+        for valve in 0..self.flows.len() {
+            if (opened & (1 << valve) != 0) || (self.flows[valve] == 0) {
+                continue; // already open, or no point opening it
+            }
             // time taken to open valve is distance to it +1
-            let new_t = time + distances[open_valves[open_valves.len() - 1]][valve] + 1;
-
-            // would opening this valve take us over the time limit?
-            if new_t < end {
-                // add pressure released in remaining time from opening this valve, and check if this is a new best
-                let new_pressure = current_pressure + (end - new_t) * flows[valve];
-                let new_best = if new_pressure > best_pressure {
-                    new_pressure
-                } else {
-                    best_pressure
-                };
-
-                // run again with updated values (after adding new valve to the list of opened ones)
-                let mut new_valves = open_valves.clone();
-                new_valves.push(valve);
-                best_pressure = run(
-                    flows,
-                    distances,
-                    new_t,
-                    end,
-                    new_valves,
-                    new_pressure,
-                    new_best,
-                );
+            let cost = self.distances[position][valve].saturating_add(1);
+            if cost < time_left {
+                // add pressure released in remaining time from opening this valve
+                let new_time = time_left - cost;
+                let new_pressure = pressure + new_time * self.flows[valve];
+                self.route.push((valve, self.time - new_time));
+                self.visit(valve, new_time, opened | (1 << valve), new_pressure);
+                self.route.pop();
             }
         }
     }
-    best_pressure
-}
-
-/// recursively compute valve opening order with the best total pressure
-/// nansty copy/pasete of p1 with additionanl loop over current position/time for
-/// one of N agents
-/// Slow - took >2hrs to run.
-#[allow(clippy::too_many_arguments)]
-fn run_p2(
-    flows: &Vec<u32>,             // pressure of each valve (when open)
-    distances: &Vec<Vec<u32>>,    // distance from each valve to each other valve
-    times: Vec<u32>,              // current time for each agent
-    positions: Vec<usize>,        // most recently visited valve for each agent
-    end: u32,                     // end time
-    remaining_valves: Vec<usize>, // valves that have NOT been turned on so far
-    current_pressure: u32, // if no more valves were turned on, total pressure achieved at end time
-    mut best_pressure: u32, // best total pressure achieved so far
-) -> u32 {
-    // candidate valve to visit next
-    for valve in &remaining_valves {
-        for agent_idx in 0..positions.len() {
-            // time taken to open valve is distance to it +1
-            let new_t = times[agent_idx] + distances[positions[agent_idx]][*valve] + 1;
-
-            // would opening this valve take us over the time limit?
-            if new_t < end {
-                // add pressure released in remaining time from opening this valve, and check if this is a new best
-                let new_pressure = current_pressure + (end - new_t) * flows[*valve];
-                let new_best = if new_pressure > best_pressure {
-                    new_pressure
-                } else {
-                    best_pressure
-                };
-
-                // run again with updated values (after adding new valve to the list of opened ones)
-                let mut new_valves = remaining_valves.clone();
-                new_valves.retain(|v| *v != *valve);
-                let mut new_positions = positions.clone();
-                new_positions[agent_idx] = *valve;
-                let mut new_times = times.clone();
-                new_times[agent_idx] = new_t;
-
-                best_pressure = run_p2(
-                    flows,
-                    distances,
-                    new_times,
-                    new_positions,
-                    end,
-                    new_valves.to_vec(),
-                    new_pressure,
-                    new_best,
-                );
+}
+
+/// best total pressure that can be released in `time` minutes from `start` for every
+/// set of opened valves (indexed by bitmask, bit i = valve i), and the order to open
+/// the valves in to get it
+fn best_per_subset(
+    flows: &[u32],
+    distances: &[Vec<u32>],
+    start: usize,
+    time: u32,
+) -> (Vec<u32>, Vec<Vec<(usize, u32)>>) {
+    let mut search = Search {
+        flows,
+        distances,
+        time,
+        best: vec![0; 1 << flows.len()],
+        routes: vec![Vec::new(); 1 << flows.len()],
+        route: Vec::new(),
+        seen: HashMap::new(),
+    };
+    search.visit(start, time, 0, 0);
+    (search.best, search.routes)
+}
+
+/// for every bitmask, the best of `best` over all its subsets
+fn best_of_subsets(best: &[u32], n_valves: usize) -> Vec<u32> {
+    let mut result = best.to_vec();
+    for valve in 0..n_valves {
+        for mask in 0..result.len() {
+            if mask & (1 << valve) != 0 {
+                result[mask] = result[mask].max(result[mask ^ (1 << valve)]);
             }
         }
     }
-    best_pressure
+    result
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let all_valves = parse_valves(input);
-    let (good_valves, distances) = path_lengths(&all_valves);
-    let flows = good_valves
-        .iter()
-        .map(|v| all_valves.get(v).unwrap().flow_rate)
-        .collect_vec();
-    let start_idx = good_valves.iter().position(|v| v == "AA").unwrap();
+/// all subsets of a bitmask (including itself and the empty set)
+fn submasks(mask: usize) -> impl Iterator<Item = usize> {
+    std::iter::successors(Some(mask), move |sub| (*sub > 0).then(|| (sub - 1) & mask))
+}
 
-    let best_total = run(&flows, &distances, 0, 30, vec![start_idx], 0, 0);
-    Some(best_total)
+/// best total pressure for `agents` agents opening disjoint sets of valves, and the set
+/// each agent opens
+fn split_between(best: &[u32], n_valves: usize, agents: usize) -> (u32, Vec<usize>) {
+    // within[k][mask] is the best total for k agents only opening valves in mask
+    let mut within = vec![vec![0; best.len()]];
+    if agents > 1 {
+        within.push(best_of_subsets(best, n_valves));
+    }
+    for k in 2..agents {
+        let fewer = &within[k - 1];
+        let more = (0..best.len())
+            .map(|mask| {
+                submasks(mask)
+                    .map(|sub| best[sub] + fewer[mask ^ sub])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        within.push(more);
+    }
+
+    // hand out the valves one agent at a time, leaving the rest for the others
+    let mut remaining = best.len() - 1;
+    let mut total = 0;
+    let mut sets = Vec::new();
+    for others in (0..agents).rev() {
+        let set = submasks(remaining)
+            .max_by_key(|sub| best[*sub] + within[others][remaining ^ sub])
+            .unwrap_or(0);
+        total += best[set];
+        sets.push(set);
+        remaining ^= set;
+    }
+    (total, sets)
+}
+
+/// valves one agent opens, in order, with the minute each is opened
+type Schedule = Vec<(String, u32)>;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Plan {
+    total: u32,
+    schedules: Vec<Schedule>, // one per agent
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let all_valves = parse_valves(input);
-    let (good_valves, distances) = path_lengths(&all_valves);
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (idx, schedule) in self.schedules.iter().enumerate() {
+            let opens = schedule
+                .iter()
+                .map(|(valve, minute)| format!("{valve} (minute {minute})"))
+                .join(", ");
+            writeln!(f, "agent {}: {opens}", idx + 1)?;
+        }
+        write!(f, "total pressure released: {}", self.total)
+    }
+}
+
+/// best plan for `agents` agents that all start at valve `start` and have `time`
+/// minutes, None if there's no valve called `start`
+fn optimise(
+    valves: &HashMap<String, Valve>,
+    start: &str,
+    agents: usize,
+    time: u32,
+) -> Option<Plan> {
+    let (good_valves, distances) = path_lengths(valves, start)?;
     let flows = good_valves
         .iter()
-        .map(|v| all_valves.get(v).unwrap().flow_rate)
+        .map(|v| valves.get(v).unwrap().flow_rate)
         .collect_vec();
-    let start_idx = good_valves.iter().position(|v| v == "AA").unwrap();
+    let start_idx = good_valves.iter().position(|v| v == start)?;
+
+    // the agents open disjoint sets of valves, so combine the best each can do alone
+    let (best, routes) = best_per_subset(&flows, &distances, start_idx, time);
+    let (total, sets) = split_between(&best, flows.len(), agents);
+    let schedules = sets
+        .iter()
+        .map(|set| {
+            routes[*set]
+                .iter()
+                .map(|(valve, minute)| (good_valves[*valve].clone(), *minute))
+                .collect()
+        })
+        .collect();
+    Some(Plan { total, schedules })
+}
+
+/// what an agent does during one minute
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Move(String), // to a neighbouring valve
+    Open,         // the valve the agent is at
+    Wait,
+}
+
+/// minute-by-minute actions that carry out an agent's schedule, taking the shortest
+/// route to each valve. None if a valve can't be reached.
+fn expand(
+    valves: &HashMap<String, Valve>,
+    start: &str,
+    schedule: &Schedule,
+) -> Option<Vec<Action>> {
+    let mut actions = Vec::new();
+    let mut position = start.to_string();
+    for (valve, _) in schedule {
+        let (path, _) = dijkstra(
+            &position,
+            |v| {
+                valves
+                    .get(v)
+                    .map_or(&[] as &[String], |valve| &valve.tunnels)
+                    .iter()
+                    .map(|tunnel| (tunnel.to_string(), 1))
+                    .collect::<Vec<(String, usize)>>()
+            },
+            |v| v == valve,
+        )?;
+        actions.extend(path.into_iter().skip(1).map(Action::Move));
+        actions.push(Action::Open);
+        position = valve.clone();
+    }
+    Some(actions)
+}
+
+/// the valves that are open at the start of a minute, as written in the puzzle text
+fn describe_open(open: &[&str], pressure: u32) -> String {
+    match open {
+        [] => String::from("No valves are open."),
+        [valve] => format!("Valve {valve} is open, releasing {pressure} pressure."),
+        [first, second] => {
+            format!("Valves {first} and {second} are open, releasing {pressure} pressure.")
+        }
+        [rest @ .., last] => format!(
+            "Valves {}, and {last} are open, releasing {pressure} pressure.",
+            rest.join(", ")
+        ),
+    }
+}
+
+/// an agent doing something, e.g. "You open valve DD." or "The elephant moves to valve II."
+fn describe_action(agent: usize, action: &str, valve: &str) -> String {
+    match agent {
+        0 => format!("You {action} valve {valve}."),
+        _ => {
+            let subject = match agent {
+                1 => String::from("The elephant"),
+                n => format!("Elephant {n}"),
+            };
+            // "open" -> "opens", "move to" -> "moves to"
+            let verb = match action.split_once(' ') {
+                Some((verb, rest)) => format!("{verb}s {rest}"),
+                None => format!("{action}s"),
+            };
+            format!("{subject} {verb} valve {valve}.")
+        }
+    }
+}
+
+/// result of replaying a proposed set of actions
+struct Replay {
+    total: u32,        // pressure released over the whole time
+    narration: String, // minute-by-minute description, in the style of the puzzle text
+}
+
+/// check agents starting at `start` can carry out `actions` (one list per agent) in
+/// `time` minutes, moving only through tunnels and opening each valve once, and total
+/// the pressure released
+fn replay(
+    valves: &HashMap<String, Valve>,
+    start: &str,
+    time: u32,
+    actions: &[Vec<Action>],
+) -> Result<Replay, String> {
+    if !valves.contains_key(start) {
+        return Err(format!("no valve called {start}"));
+    }
+    if let Some(agent) = actions.iter().position(|a| a.len() > time as usize) {
+        return Err(format!(
+            "agent {} has {} actions, but there are only {time} minutes",
+            agent + 1,
+            actions[agent].len()
+        ));
+    }
+
+    let mut positions = vec![start.to_string(); actions.len()];
+    let mut open = Vec::<String>::new(); // kept sorted
+    let mut total = 0;
+    let mut minutes = Vec::new();
+    for minute in 1..=time {
+        let open_now = open.iter().map(|v| v.as_str()).collect_vec();
+        let pressure = open_now.iter().map(|v| valves[*v].flow_rate).sum();
+        total += pressure;
+        let mut lines = vec![
+            format!("== Minute {minute} =="),
+            describe_open(&open_now, pressure),
+        ];
+
+        // valves opened this minute only release pressure from the next one
+        let mut opened = Vec::new();
+        for (agent, agent_actions) in actions.iter().enumerate() {
+            let position = &mut positions[agent];
+            let err = |message: String| format!("minute {minute}, agent {}: {message}", agent + 1);
+            match agent_actions
+                .get(minute as usize - 1)
+                .unwrap_or(&Action::Wait)
+            {
+                Action::Move(valve) => {
+                    if !valves[position.as_str()].tunnels.contains(valve) {
+                        return Err(err(format!("no tunnel from {position} to {valve}")));
+                    }
+                    lines.push(describe_action(agent, "move to", valve));
+                    *position = valve.clone();
+                }
+                Action::Open => {
+                    if open.contains(position) || opened.contains(position) {
+                        return Err(err(format!("valve {position} is already open")));
+                    }
+                    lines.push(describe_action(agent, "open", position));
+                    opened.push(position.clone());
+                }
+                Action::Wait => {}
+            }
+        }
+        open.extend(opened);
+        open.sort();
+        minutes.push(lines.join("\n"));
+    }
+    Ok(Replay {
+        total,
+        narration: minutes.join("\n\n"),
+    })
+}
+
+/// most pressure `agents` can release in `time` minutes starting from valve AA
+fn most_pressure(input: &str, agents: usize, time: u32) -> Result<u32, Error> {
+    let valves = parse_valves(input)?;
+    optimise(&valves, "AA", agents, time)
+        .map(|plan| plan.total)
+        .ok_or(Error::NoValve(String::from("AA")))
+}
 
-    // possible next valves (remaining) = all valves except the start one AA, whih always has 0 flow
-    let mut remaining_valves = (0..flows.len()).collect_vec();
-    remaining_valves.retain(|v| *v != start_idx);
+pub fn part_one(input: &str) -> Result<u32, Error> {
+    most_pressure(input, 1, 30)
+}
 
-    let best_total = run_p2(
-        &flows,
-        &distances,
-        vec![0, 0],
-        vec![start_idx, start_idx],
-        26,
-        remaining_valves,
-        0,
-        0,
-    );
-    Some(best_total)
+pub fn part_two(input: &str) -> Result<u32, Error> {
+    most_pressure(input, 2, 26)
 }
 
 fn main() {
+    // `cargo solve 16 -- --plan` prints which valves to open and when, for the puzzle
+    // (you and the elephant) or a variant, e.g. `--agents 3 --minutes 20 --start BB`.
+    // Add `--explain` to also print what happens every minute.
+    let mut args = pico_args::Arguments::from_env();
+    let plan = args.contains("--plan");
+    let agents = args.opt_value_from_str("--agents").unwrap();
+    let minutes = args.opt_value_from_str("--minutes").unwrap();
+    let start: Option<String> = args.opt_value_from_str("--start").unwrap();
+    let explain = args.contains("--explain");
+
     let input = &advent_of_code::read_file("inputs", 16);
     advent_of_code::solve!(1, part_one, input);
     advent_of_code::solve!(2, part_two, input);
+
+    if let (true, Ok(valves)) = (plan || explain, parse_valves(input)) {
+        let start = start.unwrap_or(String::from("AA"));
+        let minutes = minutes.unwrap_or(26);
+        let Some(plan) = optimise(&valves, &start, agents.unwrap_or(2), minutes) else {
+            println!("no valve called {start}");
+            return;
+        };
+        println!("{plan}");
+        if explain {
+            let actions = plan
+                .schedules
+                .iter()
+                .map(|schedule| expand(&valves, &start, schedule).unwrap())
+                .collect_vec();
+            match replay(&valves, &start, minutes, &actions) {
+                Ok(replay) => println!(
+                    "\n{}\n\nreleased {} pressure in total",
+                    replay.narration, replay.total
+                ),
+                Err(e) => println!("invalid plan: {e}"),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -212,12 +474,205 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 16);
-        assert_eq!(part_one(&input), Some(1651));
+        assert_eq!(part_one(&input), Ok(1651));
+
+        let err = part_one("Valve AA has flow rate=x; tunnels lead to valves BB").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1, column 24: could not parse \"x\" as u32: invalid digit found in string (in \"Valve AA has flow rate=x; tunnels lead to valves BB\")"
+        );
+        assert_eq!(
+            part_one("Valve BB has flow rate=0; tunnel leads to valve CC"),
+            Err(Error::NoValve(String::from("AA")))
+        );
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 16);
-        assert_eq!(part_two(&input), Some(1707));
+        assert_eq!(part_two(&input), Ok(1707));
+    }
+
+    #[test]
+    fn test_best_per_subset() {
+        let input = advent_of_code::read_file("examples", 16);
+        let all_valves = parse_valves(&input).unwrap();
+        let (good_valves, distances) = path_lengths(&all_valves, "AA").unwrap();
+        let flows = good_valves
+            .iter()
+            .map(|v| all_valves.get(v).unwrap().flow_rate)
+            .collect_vec();
+        let idx = |name: &str| good_valves.iter().position(|v| v == name).unwrap();
+        let (best, _) = best_per_subset(&flows, &distances, idx("AA"), 30);
+
+        // only opening DD: one minute to walk there, one to open it, 28 minutes of 20
+        assert_eq!(best[1 << idx("DD")], 560);
+        // the puzzle's route opens all six valves
+        let all = flows
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| **f > 0)
+            .fold(0, |mask, (i, _)| mask | (1 << i));
+        assert_eq!(best[all], 1651);
+
+        // in 26 minutes, the puzzle's split has the elephant open JJ, BB and CC
+        let (best, _) = best_per_subset(&flows, &distances, idx("AA"), 26);
+        let me = (1 << idx("DD")) | (1 << idx("HH")) | (1 << idx("EE"));
+        let elephant = (1 << idx("JJ")) | (1 << idx("BB")) | (1 << idx("CC"));
+        assert_eq!(best[me] + best[elephant], 1707);
+        assert_eq!(
+            best_of_subsets(&best, flows.len())[all ^ me],
+            best[elephant]
+        );
+    }
+
+    #[test]
+    fn test_optimise() {
+        let input = advent_of_code::read_file("examples", 16);
+        let valves = parse_valves(&input).unwrap();
+        let schedule = |opens: &[(&str, u32)]| {
+            opens
+                .iter()
+                .map(|(valve, minute)| (valve.to_string(), *minute))
+                .collect_vec()
+        };
+
+        // the routes from the puzzle text
+        let plan = optimise(&valves, "AA", 1, 30).unwrap();
+        assert_eq!(
+            plan.schedules,
+            vec![schedule(&[
+                ("DD", 2),
+                ("BB", 5),
+                ("JJ", 9),
+                ("HH", 17),
+                ("EE", 21),
+                ("CC", 24)
+            ])]
+        );
+        let plan = optimise(&valves, "AA", 2, 26).unwrap();
+        assert_eq!(plan.total, 1707);
+        assert_eq!(
+            plan.schedules.iter().sorted().collect_vec(),
+            vec![
+                &schedule(&[("DD", 2), ("HH", 7), ("EE", 11)]),
+                &schedule(&[("JJ", 3), ("BB", 7), ("CC", 9)]),
+            ]
+        );
+
+        // more agents never do worse
+        let totals = (1..=4)
+            .map(|agents| optimise(&valves, "AA", agents, 26).unwrap().total)
+            .collect_vec();
+        assert!(totals.windows(2).all(|t| t[0] <= t[1]));
+        let plan = optimise(&valves, "JJ", 3, 10).unwrap();
+        assert_eq!(plan.schedules.len(), 3);
+        // between them, three agents can reach every valve in time
+        let opened = plan.schedules.iter().flatten().map(|(v, _)| v).unique();
+        assert_eq!(opened.count(), 6);
+        assert_eq!(optimise(&valves, "ZZ", 1, 30), None);
+        assert_eq!(optimise(&valves, "AA", 0, 30).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_replay() {
+        let input = advent_of_code::read_file("examples", 16);
+        let valves = parse_valves(&input).unwrap();
+        let moves = |route: &str| {
+            route
+                .split(' ')
+                .map(|step| match step {
+                    "open" => Action::Open,
+                    "wait" => Action::Wait,
+                    valve => Action::Move(valve.to_string()),
+                })
+                .collect_vec()
+        };
+
+        // the route from the puzzle text
+        let you = moves(
+            "DD open CC BB open AA II JJ open II AA DD EE FF GG HH open GG FF EE open DD CC open",
+        );
+        let result = replay(&valves, "AA", 30, &[you]).unwrap();
+        assert_eq!(result.total, 1651);
+        assert!(result.narration.starts_with(
+            "== Minute 1 ==
+No valves are open.
+You move to valve DD.
+
+== Minute 2 ==
+No valves are open.
+You open valve DD.
+
+== Minute 3 ==
+Valve DD is open, releasing 20 pressure.
+You move to valve CC.
+
+"
+        ));
+        assert!(result.narration.contains(
+            "== Minute 5 ==
+Valve DD is open, releasing 20 pressure.
+You open valve BB.
+
+== Minute 6 ==
+Valves BB and DD are open, releasing 33 pressure.
+You move to valve AA.
+
+"
+        ));
+        assert!(result.narration.ends_with(
+            "== Minute 30 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure."
+        ));
+
+        // with the elephant
+        let you = moves("II JJ open II AA BB open CC open");
+        let elephant = moves("DD open EE FF GG HH open GG FF EE open");
+        let result = replay(&valves, "AA", 26, &[you, elephant]).unwrap();
+        assert_eq!(result.total, 1707);
+        assert!(result.narration.starts_with(
+            "== Minute 1 ==
+No valves are open.
+You move to valve II.
+The elephant moves to valve DD.
+
+== Minute 2 ==
+No valves are open.
+You move to valve JJ.
+The elephant opens valve DD.
+
+"
+        ));
+
+        // planned schedules replay to the same total
+        let plan = optimise(&valves, "AA", 2, 26).unwrap();
+        let actions = plan
+            .schedules
+            .iter()
+            .map(|schedule| expand(&valves, "AA", schedule).unwrap())
+            .collect_vec();
+        assert_eq!(replay(&valves, "AA", 26, &actions).unwrap().total, 1707);
+
+        // invalid moves
+        let check = |actions: &[&str], time| {
+            let actions = actions.iter().map(|a| moves(a)).collect_vec();
+            replay(&valves, "AA", time, &actions).err()
+        };
+        assert_eq!(check(&["DD EE FF wait GG HH"], 30), None);
+        assert_eq!(
+            check(&["DD CC AA"], 30),
+            Some(String::from("minute 3, agent 1: no tunnel from CC to AA"))
+        );
+        assert_eq!(
+            check(&["DD open", "DD open"], 30),
+            Some(String::from("minute 2, agent 2: valve DD is already open"))
+        );
+        assert_eq!(
+            check(&["wait", "DD open BB"], 2),
+            Some(String::from(
+                "agent 2 has 3 actions, but there are only 2 minutes"
+            ))
+        );
     }
 }
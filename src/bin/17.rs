@@ -1,150 +1,137 @@
+use advent_of_code::helpers::{blocks, find_cycle, ParseError};
 use itertools::Itertools;
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut cave = Cave::new(input);
-    let n_rocks = 2022;
-    for _ in 0..n_rocks {
-        cave.step();
+/// why the tower height couldn't be worked out
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    NoCycle(usize), // no repeating pattern within this many rocks
+}
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::NoCycle(rocks) => write!(f, "the tower doesn't repeat within {rocks} rocks"),
+        }
     }
-    Some(cave.height())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut cave = Cave::new(input);
-    let n_rocks = 10000; // initial rocks to simulate to find a pattern
+pub fn part_one(input: &str) -> Result<usize, ParseError> {
+    let mut cave = Cave::new(input, Config::default())?;
+    let n_rocks = 2022;
     for _ in 0..n_rocks {
         cave.step();
     }
+    Ok(cave.height())
+}
 
+pub fn part_two(input: &str) -> Result<i64, Error> {
     // search for a repeating pattern -> two equal states, where a state contains
     // the next shape that will appear, the index in the jet directions, and the height
     // to the topmost shape in each column
-    let mut pattern_start = 0;
-    let mut pattern_end = 0;
-    'outer: for left in 0..(n_rocks - 1) {
-        for right in (left + 1..n_rocks).rev() {
-            if cave.states[left] == cave.states[right] {
-                println!("{left} to {right}");
-                pattern_start = left;
-                pattern_end = right;
-                break 'outer;
-            }
-        }
-    }
+    let mut cave = Cave::new(input, Config::default())?;
+    let height = |cave: &Cave| cave.height() as i64;
+    let max_rocks = 10000;
+    let cycle = find_cycle(&mut cave, max_rocks, Cave::step, Cave::get_state, height)
+        .ok_or(Error::NoCycle(max_rocks))?;
 
     // compute height from number of repeating cycles
-    let n_rocks: usize = 1000000000000;
-    let pattern_len = pattern_end - pattern_start;
-    let cycles = (n_rocks - pattern_start) / pattern_len;
-    let remaining = (n_rocks - pattern_start) % pattern_len;
-
-    let height_per_cycle = cave.heights[pattern_end - 1] - cave.heights[pattern_start - 1];
-    let height_start = cave.heights[pattern_start - 1];
-    let height_end = cave.heights[pattern_start + remaining - 1] - cave.heights[pattern_start - 1];
-
-    Some(height_start + (height_per_cycle * cycles) + height_end)
+    Ok(cycle.value_at(1000000000000))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Left,
     Right,
-    Up,
-    Down,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
-struct Point {
-    x: i64,
-    y: i64,
-}
-impl Point {
-    fn step(&self, dir: Direction, n: i64) -> Point {
-        match dir {
-            Direction::Left => Point {
-                x: self.x - n,
-                y: self.y,
-            },
-            Direction::Right => Point {
-                x: self.x + n,
-                y: self.y,
-            },
-            Direction::Up => Point {
-                x: self.x,
-                y: self.y + n,
-            },
-            Direction::Down => Point {
-                x: self.x,
-                y: self.y - n,
-            },
-        }
-    }
-}
+/// settled rock in one row of the chamber, bit x is set if column x is filled
+type Row = u32;
 
+/// a rock as one bitmask per row, bottom row first. Bit x is set if the rock
+/// covers column x (counting from the rock's left edge until it appears in the chamber).
 #[derive(Debug, Clone)]
 struct Rock {
-    points: Vec<Point>,
+    rows: Vec<Row>,
 }
 impl Rock {
-    fn base(&self) -> i64 {
-        // lowest y value
-        self.points.iter().min_by_key(|p| p.y).unwrap().y
-    }
-
-    fn top(&self) -> i64 {
-        // max y value
-        self.points.iter().max_by_key(|p| p.y).unwrap().y
-    }
-
-    fn left(&self) -> i64 {
-        // lowest x value
-        self.points.iter().min_by_key(|p| p.x).unwrap().x
-    }
-
-    fn right(&self) -> i64 {
-        // max x value
-        self.points.iter().max_by_key(|p| p.x).unwrap().x
+    /// parse a rock drawn with # for rock and . for gaps, top row first
+    fn parse(text: &str) -> Result<Rock, ParseError> {
+        let mut rows = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let mut row = 0;
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' if x < Row::BITS as usize => row |= 1 << x,
+                    '#' => {
+                        return Err(ParseError::new(line, x + 1, "rock is too wide").offset(idx + 1))
+                    }
+                    '.' => {}
+                    _ => {
+                        return Err(ParseError::new(line, x + 1, "expected # or .").offset(idx + 1))
+                    }
+                }
+            }
+            rows.push(row);
+        }
+        if rows.iter().all(|r| *r == 0) {
+            return Err(ParseError::new(text, 1, "rock has no #").offset(1));
+        }
+        // a rock rests on its bottom row and the tower height comes from its top row
+        for (idx, edge) in [(0, "top"), (rows.len() - 1, "bottom")] {
+            if rows[idx] == 0 {
+                let line = text.lines().nth(idx).unwrap_or("");
+                return Err(
+                    ParseError::new(line, 1, format!("rock's {edge} row has no #")).offset(idx + 1),
+                );
+            }
+        }
+        rows.reverse();
+        Ok(Rock { rows })
     }
 
-    fn shift_y(&self, y: i64) -> Rock {
-        // shift rock so base is at y
-        let n: i64 = (y as i64) - (self.base() as i64);
-        if n > 0 {
-            self.step(Direction::Up, n)
-        } else {
-            self.step(Direction::Down, n)
-        }
+    /// number of columns from the rock's left edge to its right edge
+    fn width(&self) -> usize {
+        let all = self.rows.iter().fold(0, |acc, r| acc | r);
+        (Row::BITS - all.leading_zeros()) as usize
     }
 
-    fn step(&self, dir: Direction, n: i64) -> Rock {
+    /// the rock moved `n` columns to the right
+    fn shift(&self, n: usize) -> Rock {
         Rock {
-            points: self.points.iter().map(|p| p.step(dir, n)).collect_vec(),
+            rows: self.rows.iter().map(|r| r << n).collect(),
         }
     }
-    fn collides(&self, other: &Rock) -> bool {
-        if (other.base() > self.top()) | (self.base() > other.top()) {
-            return false;
-        }
-        if (other.left() > self.right()) | (self.left() > other.right()) {
-            return false;
-        }
-        for me in &self.points {
-            for you in &other.points {
-                if me == you {
-                    return true;
-                }
+
+    /// move the rock one column, None if that would take it through a wall of a
+    /// chamber `width` columns wide
+    fn push(&self, dir: Direction, width: usize) -> Option<Rock> {
+        let rows = match dir {
+            Direction::Left if self.rows.iter().all(|r| r & 1 == 0) => {
+                self.rows.iter().map(|r| r >> 1).collect_vec()
             }
-        }
-        false
+            Direction::Right if self.rows.iter().all(|r| r & (1 << (width - 1)) == 0) => {
+                self.rows.iter().map(|r| r << 1).collect_vec()
+            }
+            _ => return None,
+        };
+        Some(Rock { rows })
     }
 }
 
 impl std::fmt::Display for Rock {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let all = self.rows.iter().fold(0, |acc, r| acc | r);
+        let left = all.trailing_zeros() as usize;
         let mut text: String = String::new();
-        for y in (self.base()..(self.top() + 1)).rev() {
-            for x in self.left()..(self.right() + 1) {
-                if self.points.contains(&Point { x, y }) {
+        for row in self.rows.iter().rev() {
+            for x in left..self.width() {
+                if row & (1 << x) != 0 {
                     text.push('#')
                 } else {
                     text.push('.')
@@ -156,191 +143,268 @@ impl std::fmt::Display for Rock {
     }
 }
 
-fn get_rock_types() -> Vec<Rock> {
-    vec![
-        Rock {
-            // -
-            points: vec![
-                Point { x: 2, y: 3 },
-                Point { x: 3, y: 3 },
-                Point { x: 4, y: 3 },
-                Point { x: 5, y: 3 },
-            ],
-        },
-        Rock {
-            // +
-            points: vec![
-                Point { x: 3, y: 3 },
-                Point { x: 2, y: 4 },
-                Point { x: 3, y: 4 },
-                Point { x: 4, y: 4 },
-                Point { x: 3, y: 5 },
-            ],
-        },
-        Rock {
-            // L
-            points: vec![
-                Point { x: 2, y: 3 },
-                Point { x: 3, y: 3 },
-                Point { x: 4, y: 3 },
-                Point { x: 4, y: 4 },
-                Point { x: 4, y: 5 },
-            ],
-        },
-        Rock {
-            // |
-            points: vec![
-                Point { x: 2, y: 3 },
-                Point { x: 2, y: 4 },
-                Point { x: 2, y: 5 },
-                Point { x: 2, y: 6 },
-            ],
-        },
-        Rock {
-            // ■
-            points: vec![
-                Point { x: 2, y: 3 },
-                Point { x: 3, y: 3 },
-                Point { x: 2, y: 4 },
-                Point { x: 3, y: 4 },
-            ],
-        },
-    ]
+/// the rocks from the puzzle, in the order they fall
+const PUZZLE_ROCKS: &str = "####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
+
+/// layout of the chamber and the rocks that fall into it
+struct Config {
+    width: usize,      // number of columns in the chamber
+    spawn_left: usize, // gap between the left wall and a new rock
+    spawn_gap: usize,  // gap between the highest rock (or the floor) and a new rock
+    rocks: Vec<Rock>,  // shapes that fall, repeating in this order
+}
+impl Config {
+    /// `drawings` has the rocks drawn like the puzzle text, separated by blank lines
+    fn new(
+        width: usize,
+        spawn_left: usize,
+        spawn_gap: usize,
+        drawings: &str,
+    ) -> Result<Config, ParseError> {
+        if (width == 0) || (width > Row::BITS as usize) {
+            return Err(ParseError::new(
+                drawings.lines().next().unwrap_or(""),
+                1,
+                format!("chamber must be 1 to {} columns wide", Row::BITS),
+            ));
+        }
+        let rocks = blocks(drawings)
+            .into_iter()
+            .map(|(offset, text)| {
+                let rock = Rock::parse(text).map_err(|e| e.offset(offset))?;
+                if spawn_left + rock.width() > width {
+                    return Err(ParseError::new(
+                        text.lines().next().unwrap_or(""),
+                        1,
+                        format!("rock doesn't fit in a chamber {width} wide at {spawn_left} from the wall"),
+                    )
+                    .offset(offset + 1));
+                }
+                Ok(rock)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if rocks.is_empty() {
+            return Err(ParseError::new(drawings, 1, "expected at least one rock"));
+        }
+        Ok(Config {
+            width,
+            spawn_left,
+            spawn_gap,
+            rocks,
+        })
+    }
+}
+
+impl Default for Config {
+    /// the chamber from the puzzle
+    fn default() -> Config {
+        Config::new(7, 2, 3, PUZZLE_ROCKS).unwrap()
+    }
 }
 
-fn get_jet_directions(input: &str) -> Vec<Direction> {
-    input
-        .trim()
-        .chars()
-        .map(|c| match c {
-            '>' => Direction::Right,
-            '<' => Direction::Left,
-            _ => panic!("unexpected direction {c}"),
+fn get_jet_directions(input: &str) -> Result<Vec<Direction>, ParseError> {
+    let line = input.trim();
+    if line.is_empty() {
+        return Err(ParseError::new(line, 1, "expected jets of hot gas"));
+    }
+    line.chars()
+        .enumerate()
+        .map(|(idx, c)| match c {
+            '>' => Ok(Direction::Right),
+            '<' => Ok(Direction::Left),
+            _ => Err(ParseError::new(line, idx + 1, "expected < or >")),
         })
-        .collect_vec()
+        .collect()
 }
 
 struct Cave {
-    rock_types: Vec<Rock>,
+    config: Config,
     jet_directions: Vec<Direction>,
-    width: usize,
-    rocks: Vec<Rock>,
+    rows: Vec<Row>, // settled rock in each row from the floor up, the top row is never empty
+    col_tops: Vec<usize>, // height of the highest settled rock in each column
     jet_idx: usize,
     type_idx: usize,
-    states: Vec<State>,
-    heights: Vec<usize>,
 }
 impl Cave {
-    fn new(input: &str) -> Cave {
-        Cave {
-            rock_types: get_rock_types(),
-            jet_directions: get_jet_directions(input),
-            width: 7,
-            rocks: Vec::<Rock>::new(),
+    fn new(input: &str, config: Config) -> Result<Cave, ParseError> {
+        Ok(Cave {
+            jet_directions: get_jet_directions(input)?,
+            rows: Vec::new(),
+            col_tops: vec![0; config.width],
+            config,
             jet_idx: 0,
             type_idx: 0,
-            states: Vec::<State>::new(),
-            heights: Vec::<usize>::new(),
-        }
+        })
+    }
+
+    /// whether a rock with its bottom row at height y overlaps settled rock
+    fn collides(&self, rock: &Rock, y: usize) -> bool {
+        rock.rows
+            .iter()
+            .zip(self.rows.iter().skip(y))
+            .any(|(rock_row, row)| rock_row & row != 0)
     }
 
     fn step(&mut self) {
-        let height = if self.rocks.is_empty() {
-            3
-        } else {
-            self.rocks.iter().max_by_key(|r| r.top()).unwrap().top() + 4
-        };
+        self.drop_rock(|_, _, _| {});
+    }
 
-        let mut new_rock = self.rock_types[self.type_idx].shift_y(height);
-        let mut rock_placed = false;
-        let mut jet_move = true;
-        while !rock_placed {
-            let direction = match jet_move {
-                true => self.jet_directions[self.jet_idx],
-                false => Direction::Down,
+    /// drop the next rock until it comes to rest. `frame` is called with the cave,
+    /// a description of the move (as in the puzzle text) and the falling rock with the
+    /// height of its bottom row, when it appears and after every move.
+    fn drop_rock(&mut self, mut frame: impl FnMut(&Cave, &str, Option<(&Rock, usize)>)) {
+        let mut rock = self.config.rocks[self.type_idx].shift(self.config.spawn_left);
+        let mut y = self.height() + self.config.spawn_gap;
+        let appears = match self.rows.is_empty() {
+            true => "The first rock begins falling",
+            false => "A new rock begins falling",
+        };
+        frame(self, appears, Some((&rock, y)));
+        loop {
+            // pushed by the jet, unless it would hit a wall or another rock
+            let direction = self.jet_directions[self.jet_idx];
+            self.jet_idx = (self.jet_idx + 1) % self.jet_directions.len();
+            let pushed = rock
+                .push(direction, self.config.width)
+                .filter(|pushed| !self.collides(pushed, y));
+            let push = match (direction, pushed.is_some()) {
+                (Direction::Left, true) => "Jet of gas pushes rock left",
+                (Direction::Left, false) => "Jet of gas pushes rock left, but nothing happens",
+                (Direction::Right, true) => "Jet of gas pushes rock right",
+                (Direction::Right, false) => "Jet of gas pushes rock right, but nothing happens",
             };
-
-            if (new_rock.base() == 0) & (direction == Direction::Down) {
-                // rock will collide with floor and stop moving
-                rock_placed = true;
-            } else if !(((new_rock.left() == 0) & (direction == Direction::Left))
-                | ((new_rock.right() == self.width as i64 - 1) & (direction == Direction::Right)))
-            {
-                // horrific long condition means: if rock will NOT collide with the walls on this step
-                // will it collide with any other rocks?
-                let mut next_step = new_rock.step(direction, 1);
-
-                for r in self.rocks.iter().rev() {
-                    // reverse order above as more likely to collide with recently placed rocks
-                    if next_step.collides(r) {
-                        if direction == Direction::Down {
-                            // collide with another rock whilst moving down means movement stops
-                            rock_placed = true;
-                        }
-                        // a collision occurred so reset this step
-                        next_step = new_rock.clone();
-                        break;
-                    }
-                }
-                new_rock = next_step;
+            if let Some(pushed) = pushed {
+                rock = pushed;
             }
-
-            // update the direction indicators for next iteration
-            jet_move = !jet_move;
-            if jet_move {
-                self.jet_idx = (self.jet_idx + 1) % self.jet_directions.len();
+            frame(self, push, Some((&rock, y)));
+            // then falls one row, unless it's reached the floor or another rock
+            if (y == 0) || self.collides(&rock, y - 1) {
+                break;
             }
+            y -= 1;
+            frame(self, "Rock falls 1 unit", Some((&rock, y)));
         }
 
         // add the new rock and update the next step
-        self.rocks.push(new_rock);
-        self.type_idx = (self.type_idx + 1) % self.rock_types.len();
+        for (idx, rock_row) in rock.rows.iter().enumerate() {
+            if *rock_row == 0 {
+                continue; // keep the top row non-empty for rocks drawn with blank rows
+            }
+            while y + idx >= self.rows.len() {
+                self.rows.push(0);
+            }
+            self.rows[y + idx] |= rock_row;
+            for (x, top) in self.col_tops.iter_mut().enumerate() {
+                if rock_row & (1 << x) != 0 {
+                    *top = (*top).max(y + idx + 1);
+                }
+            }
+        }
+        self.type_idx = (self.type_idx + 1) % self.config.rocks.len();
+        frame(self, "Rock falls 1 unit, causing it to come to rest", None);
+    }
 
-        self.heights.push(self.height());
-        self.states.push(self.get_state());
+    /// draw the chamber like the puzzle text, with the falling rock (and the height of
+    /// its bottom row) as @ and settled rocks as #. Only the top `rows` rows are drawn,
+    /// or all of them if None.
+    fn render(&self, falling: Option<(&Rock, usize)>, rows: Option<usize>) -> String {
+        let top = falling
+            .map_or(0, |(rock, y)| y + rock.rows.len())
+            .max(self.height());
+        let bottom = rows.map_or(0, |n| top.saturating_sub(n));
+        let mut lines = (bottom..top)
+            .rev()
+            .map(|y| {
+                let settled = self.rows.get(y).copied().unwrap_or(0);
+                let rock = falling
+                    .and_then(|(rock, rock_y)| rock.rows.get(y.checked_sub(rock_y)?).copied())
+                    .unwrap_or(0);
+                let cells: String = (0..self.config.width)
+                    .map(|x| match (rock & (1 << x) != 0, settled & (1 << x) != 0) {
+                        (true, _) => '@',
+                        (false, true) => '#',
+                        (false, false) => '.',
+                    })
+                    .collect();
+                format!("|{cells}|")
+            })
+            .collect_vec();
+        if bottom == 0 {
+            lines.push(format!("+{}+", "-".repeat(self.config.width)));
+        }
+        lines.join("\n")
     }
 
     fn height(&self) -> usize {
-        // max y value of all placed rocks
-        (self.rocks.iter().max_by_key(|r| r.top()).unwrap().top() + 1) as usize
+        // height of the tower of placed rocks (0 if no rocks placed yet)
+        self.rows.len()
     }
 
     fn get_state(&self) -> State {
         let max_height = self.height();
-        let mut col_heights = [0; 7];
-        for (idx, x) in (0..self.width).enumerate() {
-            col_heights[idx] = max_height
-                - self
-                    .rocks
-                    .iter()
-                    .map(|r| {
-                        r.points
-                            .iter()
-                            .filter(|p| p.x == x as i64)
-                            .max_by_key(|p| p.y)
-                            .unwrap_or(&Point { x: x as i64, y: 0 })
-                            .y
-                    })
-                    .max()
-                    .unwrap() as usize;
-        }
         State {
-            col_heights,
+            col_heights: self.col_tops.iter().map(|top| max_height - top).collect(),
             jet_idx: self.jet_idx,
             type_idx: self.type_idx,
         }
     }
 }
 
-#[derive(Clone, Copy, Default, Hash, Eq, PartialEq, Debug)]
+/// every frame of the first `n_rocks` rocks falling, as illustrated in the puzzle text,
+/// drawing the top `rows` rows of the chamber (all of it if None)
+fn frames(
+    input: &str,
+    config: Config,
+    n_rocks: usize,
+    rows: Option<usize>,
+) -> Result<Vec<String>, ParseError> {
+    let mut cave = Cave::new(input, config)?;
+    let mut frames = Vec::new();
+    for _ in 0..n_rocks {
+        cave.drop_rock(|cave, description, falling| {
+            frames.push(format!("{description}:\n{}", cave.render(falling, rows)));
+        });
+    }
+    Ok(frames)
+}
+
+#[derive(Clone, Default, Hash, Eq, PartialEq, Debug)]
 struct State {
-    col_heights: [usize; 7],
+    col_heights: Vec<usize>,
     jet_idx: usize,
     type_idx: usize,
 }
 
 fn main() {
+    // `cargo solve 17 -- --frames 3` draws the chamber after every move of the first 3
+    // rocks, add e.g. `--rows 20` to only draw the top 20 rows
+    let mut args = pico_args::Arguments::from_env();
+    let n_frames: Option<usize> = args.opt_value_from_str("--frames").unwrap();
+    let rows = args.opt_value_from_str("--rows").unwrap();
+
     let input = &advent_of_code::read_file("inputs", 17);
+    if let Some(n_rocks) = n_frames {
+        match frames(input, Config::default(), n_rocks, rows) {
+            Ok(frames) => println!("{}\n", frames.join("\n\n")),
+            Err(e) => println!("can't draw frames: {e}"),
+        }
+    }
     advent_of_code::solve!(1, part_one, input);
     advent_of_code::solve!(2, part_two, input);
 }
@@ -352,12 +416,185 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 17);
-        assert_eq!(part_one(&input), Some(3068));
+        assert_eq!(part_one(&input), Ok(3068));
+
+        assert_eq!(
+            part_one(">><x<").unwrap_err().to_string(),
+            "column 4: expected < or > (in \">><x<\")"
+        );
+        assert!(part_one("\n").is_err());
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 17);
-        assert_eq!(part_two(&input), Some(1514285714288));
+        assert_eq!(part_two(&input), Ok(1514285714288));
+    }
+
+    #[test]
+    fn test_rocks() {
+        let rocks = Config::default().rocks;
+        // drawings round trip
+        assert_eq!(rocks.iter().join("\n").trim_end(), PUZZLE_ROCKS);
+        assert_eq!(rocks[1].rows, vec![2, 7, 2]);
+        assert_eq!(
+            rocks[1].push(Direction::Right, 7).unwrap().rows,
+            vec![4, 14, 4]
+        );
+        assert!(rocks[1].push(Direction::Left, 7).is_none());
+        // the - is one push from the right wall after it appears
+        let pushed = rocks[0].shift(2).push(Direction::Right, 7).unwrap();
+        assert!(pushed.push(Direction::Right, 7).is_none());
+        assert_eq!(pushed.to_string(), "####\n");
+
+        // the first rocks of the example settle as drawn in the puzzle
+        let input = advent_of_code::read_file("examples", 17);
+        let mut cave = Cave::new(&input, Config::default()).unwrap();
+        for _ in 0..3 {
+            cave.step();
+        }
+        assert_eq!(cave.height(), 6);
+        assert_eq!(
+            cave.rows,
+            vec![0b0111100, 0b0001000, 0b0011100, 0b0001111, 0b0000100, 0b0000100]
+        );
+        assert_eq!(cave.col_tops, [4, 4, 6, 4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_config() {
+        // in a chamber one column wide, every rock lands on the one before
+        let mut cave = Cave::new("<>", Config::new(1, 0, 3, "#\n#\n\n#").unwrap()).unwrap();
+        for _ in 0..4 {
+            cave.step();
+        }
+        assert_eq!(cave.height(), 6);
+
+        // rocks pushed right from the spawn end up against the wall of a wider chamber
+        let config = Config::new(12, 4, 8, "###\n\n#.\n##").unwrap();
+        let mut cave = Cave::new(">", config).unwrap();
+        for _ in 0..3 {
+            cave.step();
+        }
+        assert_eq!(
+            cave.rows,
+            vec![0b111 << 9, 0b11 << 10, 0b1 << 10, 0b111 << 9]
+        );
+
+        // and in a narrower one they fill every column, so the tower repeats
+        let mut cave = Cave::new(">", Config::new(3, 0, 3, "###\n\n#.\n##").unwrap()).unwrap();
+        let height = |cave: &Cave| cave.height() as i64;
+        let cycle = find_cycle(&mut cave, 100, Cave::step, Cave::get_state, height);
+        assert_eq!(cave.rows, vec![0b111, 0b110, 0b010, 0b111]);
+        assert_eq!(cycle.unwrap().value_at(1000), 1500);
+
+        let err = |width, drawings| {
+            Config::new(width, 2, 3, drawings)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            err(7, "##\n\n#x"),
+            "line 3, column 2: expected # or . (in \"#x\")"
+        );
+        assert_eq!(
+            err(4, "#\n\n###"),
+            "line 3, column 1: rock doesn't fit in a chamber 4 wide at 2 from the wall (in \"###\")"
+        );
+        assert_eq!(
+            err(7, "..\n.."),
+            "line 1, column 1: rock has no # (in \"..\n..\")"
+        );
+        assert_eq!(
+            err(7, "#\n."),
+            "line 2, column 1: rock's bottom row has no # (in \".\")"
+        );
+        assert_eq!(
+            err(7, "..\n.#"),
+            "line 1, column 1: rock's top row has no # (in \"..\")"
+        );
+        assert_eq!(
+            err(40, "#"),
+            "column 1: chamber must be 1 to 32 columns wide (in \"#\")"
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let input = advent_of_code::read_file("examples", 17);
+        let frames = frames(&input, Config::default(), 2, None).unwrap();
+        assert_eq!(
+            frames[..3].join("\n\n"),
+            "The first rock begins falling:
+|..@@@@.|
+|.......|
+|.......|
+|.......|
++-------+
+
+Jet of gas pushes rock right:
+|...@@@@|
+|.......|
+|.......|
+|.......|
++-------+
+
+Rock falls 1 unit:
+|...@@@@|
+|.......|
+|.......|
++-------+"
+        );
+        assert_eq!(
+            frames[7..11].join("\n\n"),
+            "Jet of gas pushes rock left:
+|..@@@@.|
++-------+
+
+Rock falls 1 unit, causing it to come to rest:
+|..####.|
++-------+
+
+A new rock begins falling:
+|...@...|
+|..@@@..|
+|...@...|
+|.......|
+|.......|
+|.......|
+|..####.|
++-------+
+
+Jet of gas pushes rock left:
+|..@....|
+|.@@@...|
+|..@....|
+|.......|
+|.......|
+|.......|
+|..####.|
++-------+"
+        );
+        assert_eq!(
+            frames[5],
+            "Jet of gas pushes rock right, but nothing happens:\n|...@@@@|\n|.......|\n+-------+"
+        );
+        assert_eq!(
+            frames.last().unwrap(),
+            "Rock falls 1 unit, causing it to come to rest:
+|...#...|
+|..###..|
+|...#...|
+|..####.|
++-------+"
+        );
+
+        // the top of a taller chamber, without the floor
+        let cave_frames = super::frames(&input, Config::default(), 3, Some(2)).unwrap();
+        assert_eq!(
+            cave_frames.last().unwrap(),
+            "Rock falls 1 unit, causing it to come to rest:\n|..#....|\n|..#....|"
+        );
     }
 }
@@ -1,9 +1,9 @@
 use std::collections::VecDeque;
 
-use itertools::Itertools;
+use advent_of_code::helpers::{parse_lines, ParseError, Template};
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let (grid, n_cubes) = parse_grid(input);
+pub fn part_one(input: &str) -> Result<u32, ParseError> {
+    let (grid, n_cubes) = parse_grid(input)?;
     let mut side_count: usize = n_cubes * 6;
 
     // check for shared edges
@@ -19,12 +19,12 @@ pub fn part_one(input: &str) -> Option<u32> {
             }
         }
     }
-    Some(side_count as u32)
+    Ok(side_count as u32)
 }
 
 #[allow(clippy::needless_range_loop)]
-pub fn part_two(input: &str) -> Option<u32> {
-    let (grid, _) = parse_grid(input);
+pub fn part_two(input: &str) -> Result<u32, ParseError> {
+    let (grid, _) = parse_grid(input)?;
     let xlen = grid.len();
     let ylen = grid[0].len();
     let zlen = grid[0][0].len();
@@ -46,10 +46,10 @@ pub fn part_two(input: &str) -> Option<u32> {
             }
         }
     }
-    Some(side_count as u32)
+    Ok(side_count as u32)
 }
 
-fn shared_edges(x: usize, y: usize, z: usize, grid: &Vec<Vec<Vec<bool>>>) -> usize {
+fn shared_edges(x: usize, y: usize, z: usize, grid: &[Vec<Vec<bool>>]) -> usize {
     // how many neighbouring cubes to x,y,z?
     let xlen = grid.len();
     let ylen = grid[0].len();
@@ -88,7 +88,7 @@ fn shared_edges(x: usize, y: usize, z: usize, grid: &Vec<Vec<Vec<bool>>>) -> usi
 
 /// Breadth first search to fill around the shape with "water"
 fn bfs(
-    grid: &Vec<Vec<Vec<bool>>>,
+    grid: &[Vec<Vec<bool>>],
     queue: &mut VecDeque<Point>,
     wet: &mut Vec<Vec<Vec<bool>>>,
 ) -> Vec<Vec<Vec<bool>>> {
@@ -106,7 +106,7 @@ fn bfs(
 }
 
 /// get adjacent points to p that are NOT occupieds
-fn get_open_neighbours(p: &Point, grid: &Vec<Vec<Vec<bool>>>) -> Vec<Point> {
+fn get_open_neighbours(p: &Point, grid: &[Vec<Vec<bool>>]) -> Vec<Point> {
     let xlen = grid.len();
     let ylen = grid[0].len();
     let zlen = grid[0][0].len();
@@ -154,37 +154,35 @@ fn get_open_neighbours(p: &Point, grid: &Vec<Vec<Vec<bool>>>) -> Vec<Point> {
     neighbours
 }
 
-fn parse_points(input: &str) -> Vec<Point> {
-    input
-        .lines()
-        .map(|l| {
-            l.split(',')
-                .map(|d| d.parse::<usize>().unwrap())
-                .collect_tuple::<(usize, usize, usize)>()
-                .unwrap()
+fn parse_points(input: &str) -> Result<Vec<Point>, ParseError> {
+    let template = Template::new("{},{},{}");
+    parse_lines(input, |line| {
+        let matches = template.captures(line)?;
+        Ok(Point {
+            x: matches.get(0)?,
+            y: matches.get(1)?,
+            z: matches.get(2)?,
         })
-        .map(|origin| Point {
-            x: origin.0,
-            y: origin.1,
-            z: origin.2,
-        })
-        .collect_vec()
+    })
 }
 
-fn parse_grid(input: &str) -> (Vec<Vec<Vec<bool>>>, usize) {
+/// grid[x][y][z] is true if there's a cube at (x, y, z)
+type Grid = Vec<Vec<Vec<bool>>>;
+
+fn parse_grid(input: &str) -> Result<(Grid, usize), ParseError> {
     let border = 1; // make the grid slightly bigger than needed to allow BFS to search around the grid ("immerse the shape in water")
-    let points = parse_points(input);
+    let points = parse_points(input)?;
     let n_cubes = points.len();
-    let max_x = points.iter().max_by_key(|p| p.x).unwrap().x + 2 * border;
-    let max_y = points.iter().max_by_key(|p| p.y).unwrap().y + 2 * border;
-    let max_z = points.iter().max_by_key(|p| p.z).unwrap().z + 2 * border;
+    let max_x = points.iter().map(|p| p.x).max().unwrap_or(0) + 2 * border;
+    let max_y = points.iter().map(|p| p.y).max().unwrap_or(0) + 2 * border;
+    let max_z = points.iter().map(|p| p.z).max().unwrap_or(0) + 2 * border;
 
     let mut grid = vec![vec![vec![false; max_z + 1]; max_y + 1]; max_x + 1];
 
     for p in points {
         grid[p.x + border][p.y + border][p.z + border] = true;
     }
-    (grid, n_cubes)
+    Ok((grid, n_cubes))
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
@@ -207,12 +205,18 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 18);
-        assert_eq!(part_one(&input), Some(64));
+        assert_eq!(part_one(&input), Ok(64));
+
+        assert_eq!(
+            part_one("1,1,1\n2,-1,1").unwrap_err().to_string(),
+            "line 2, column 3: could not parse \"-1\" as usize: invalid digit found in string (in \"2,-1,1\")"
+        );
+        assert_eq!(part_one(""), Ok(0));
     }
 
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 18);
-        assert_eq!(part_two(&input), Some(58));
+        assert_eq!(part_two(&input), Ok(58));
     }
 }
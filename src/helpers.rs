@@ -2,3 +2,569 @@
  * Use this file if you want to extract helpers from your solutions.
  * Example import from this file: `use advent_of_code::helpers::example_fn;`.
  */
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// a repeating section of a simulation, found by `find_cycle`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,  // number of steps taken before the repeating section begins
+    pub length: usize, // number of steps in one repeat
+    values: Vec<i64>,  // tracked value after 0, 1, ... (start + length) steps
+}
+impl Cycle {
+    /// tracked value after `n` steps, extrapolated using the cycle where needed
+    pub fn value_at(&self, n: usize) -> i64 {
+        if n < self.values.len() {
+            return self.values[n];
+        }
+        let cycles = (n - self.start) / self.length;
+        let remaining = (n - self.start) % self.length;
+        let per_cycle = self.values[self.start + self.length] - self.values[self.start];
+        self.values[self.start + remaining] + cycles as i64 * per_cycle
+    }
+}
+
+/// step a simulation until it reaches a state it has been in before.
+/// `key` should capture everything that determines future behaviour (e.g. position
+/// in a repeating input + the shape of the surface), and `value` is the quantity to
+/// extrapolate (e.g. height), which may go down as well as up. Returns None if no repeat is found within `max_steps`.
+pub fn find_cycle<S, K: Hash + Eq>(
+    state: &mut S,
+    max_steps: usize,
+    mut step: impl FnMut(&mut S),
+    key: impl Fn(&S) -> K,
+    value: impl Fn(&S) -> i64,
+) -> Option<Cycle> {
+    // step number each key was first seen at
+    let mut seen = HashMap::new();
+    let mut values = Vec::new();
+
+    for n in 0..=max_steps {
+        values.push(value(state));
+        if let Some(start) = seen.insert(key(state), n) {
+            return Some(Cycle {
+                start,
+                length: n - start,
+                values,
+            });
+        }
+        step(state);
+    }
+    None
+}
+
+/// error from one of the parsing helpers, with the position in the input it occurred at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,   // 1-based line number (0 if the error came from a single line)
+    pub column: usize, // 1-based column (in chars) within the line
+    pub text: String,  // the offending line
+    pub message: String,
+}
+impl ParseError {
+    pub fn new(text: &str, column: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: 0,
+            column,
+            text: text.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// shift the line number, e.g. to make an error in a block relative to the whole input
+    pub fn offset(mut self, lines: usize) -> ParseError {
+        self.line += lines;
+        self
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}, ", self.line)?;
+        }
+        write!(
+            f,
+            "column {}: {} (in \"{}\")",
+            self.column, self.message, self.text
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 1-based char column of byte offset `idx` in `line`
+fn column(line: &str, idx: usize) -> usize {
+    line[..idx].chars().count() + 1
+}
+
+/// parse each line of `input` with `f`, adding the line number to any error
+pub fn parse_lines<'a, T>(
+    input: &'a str,
+    mut f: impl FnMut(&'a str) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| f(line).map_err(|e| e.offset(idx + 1)))
+        .collect()
+}
+
+/// all signed integers in a line, e.g. "x=-2, y=15" -> [-2, 15].
+/// '-' only counts as a sign if it doesn't follow a digit, so "2-4" -> [2, 4].
+pub fn ints(line: &str) -> Vec<i64> {
+    int_spans(line)
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect()
+}
+
+/// all signed integers in a line like `ints`, converted to T. Fails with the column
+/// of the first one that doesn't fit in a T.
+pub fn ints_as<T: TryFrom<i64>>(line: &str) -> Result<Vec<T>, ParseError> {
+    int_spans(line)
+        .into_iter()
+        .map(|(pos, value)| {
+            T::try_from(value).map_err(|_| {
+                ParseError::new(
+                    line,
+                    column(line, pos),
+                    format!("{value} is out of range for {}", std::any::type_name::<T>()),
+                )
+            })
+        })
+        .collect()
+}
+
+/// (byte offset, value) of each integer found by `ints`
+fn int_spans(line: &str) -> Vec<(usize, i64)> {
+    let bytes = line.as_bytes();
+    let mut values = Vec::new();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let negative = (bytes[idx] == b'-')
+            & (idx + 1 < bytes.len())
+            & ((idx == 0) || !bytes[idx - 1].is_ascii_digit());
+        let start = idx;
+        if negative && bytes[idx + 1].is_ascii_digit() {
+            idx += 1;
+        }
+        if bytes[idx].is_ascii_digit() {
+            while (idx < bytes.len()) && bytes[idx].is_ascii_digit() {
+                idx += 1;
+            }
+            // only fails on overflow, skip those
+            if let Ok(value) = line[start..idx].parse() {
+                values.push((start, value));
+            }
+        } else {
+            idx = start + 1;
+        }
+    }
+    values
+}
+
+/// split input into blocks separated by blank lines. Each block comes with the
+/// number of lines before it, to pass to `ParseError::offset`.
+pub fn blocks(input: &str) -> Vec<(usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut start: Option<(usize, usize)> = None; // (line number, byte offset) of current block
+    let mut end = 0; // byte offset of the end of the last non-blank line
+    let mut offset = 0;
+    for (idx, line) in input.split_inclusive('\n').enumerate() {
+        if line.trim().is_empty() {
+            if let Some((first_line, first_byte)) = start.take() {
+                blocks.push((first_line, &input[first_byte..end]));
+            }
+        } else {
+            if start.is_none() {
+                start = Some((idx, offset));
+            }
+            end = offset + line.trim_end_matches(['\n', '\r']).len();
+        }
+        offset += line.len();
+    }
+    if let Some((first_line, first_byte)) = start {
+        blocks.push((first_line, &input[first_byte..end]));
+    }
+    blocks
+}
+
+/// split a "key: value" line into its (trimmed) key and value
+pub fn parse_record(line: &str) -> Result<(&str, &str), ParseError> {
+    match line.find(':') {
+        Some(idx) => Ok((line[..idx].trim(), line[idx + 1..].trim())),
+        None => Err(ParseError::new(
+            line,
+            column(line, line.len()),
+            "expected \"key: value\"",
+        )),
+    }
+}
+
+/// parse every line of a block as a "key: value" record
+pub fn parse_records(block: &str) -> Result<Vec<(&str, &str)>, ParseError> {
+    parse_lines(block, parse_record)
+}
+
+/// a line format with `{}` placeholders, e.g. "move {} from {} to {}".
+/// Build once and reuse it for every line. A pattern without placeholders only
+/// matches that exact line.
+#[derive(Debug, Clone)]
+pub struct Template {
+    literals: Vec<String>, // text around the placeholders (one more than no. placeholders)
+}
+impl Template {
+    pub fn new(pattern: &str) -> Template {
+        let literals = pattern.split("{}").map(|s| s.to_string()).collect_vec();
+        // text between two placeholders (none if there are fewer than two)
+        let between = literals.get(1..literals.len() - 1).unwrap_or_default();
+        assert!(
+            between.iter().all(|l| !l.is_empty()),
+            "placeholders must be separated by some text: {pattern}"
+        );
+        Template { literals }
+    }
+
+    pub fn captures<'a>(&self, line: &'a str) -> Result<Captures<'a>, ParseError> {
+        let expected = |idx: usize, literal: &str| {
+            ParseError::new(line, column(line, idx), format!("expected \"{literal}\""))
+        };
+
+        let first = &self.literals[0];
+        if !line.starts_with(first.as_str()) {
+            return Err(expected(0, first));
+        }
+        let mut pos = first.len();
+        let mut fields = Vec::new();
+        let n_fields = self.literals.len() - 1;
+        if n_fields == 0 && line.len() > pos {
+            return Err(ParseError::new(
+                line,
+                column(line, pos),
+                "expected end of line",
+            ));
+        }
+        for (idx, literal) in self.literals[1..].iter().enumerate() {
+            let rest = &line[pos..];
+            let len = if idx + 1 == n_fields {
+                // last field: runs up to the trailing literal at the end of the line
+                match rest.strip_suffix(literal.as_str()) {
+                    Some(field) => field.len(),
+                    None => return Err(expected(line.len(), literal)),
+                }
+            } else {
+                match rest.find(literal.as_str()) {
+                    Some(len) => len,
+                    None => return Err(expected(pos, literal)),
+                }
+            };
+            fields.push((pos, &rest[..len]));
+            pos += len + literal.len();
+        }
+        Ok(Captures { line, fields })
+    }
+}
+
+/// the fields matched by a `Template`
+#[derive(Debug, Clone)]
+pub struct Captures<'a> {
+    line: &'a str,
+    fields: Vec<(usize, &'a str)>, // (byte offset in line, text) of each field
+}
+impl<'a> Captures<'a> {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn str(&self, idx: usize) -> &'a str {
+        self.fields[idx].1
+    }
+
+    /// parse field `idx` as a T
+    pub fn get<T: FromStr>(&self, idx: usize) -> Result<T, ParseError>
+    where
+        T::Err: Display,
+    {
+        let (pos, text) = self.fields[idx];
+        parse_field(self.line, pos, text)
+    }
+
+    /// parse field `idx` as a list of T separated by `sep`, e.g. "79, 98" with ", "
+    pub fn list<T: FromStr>(&self, idx: usize, sep: &str) -> Result<Vec<T>, ParseError>
+    where
+        T::Err: Display,
+    {
+        let (mut pos, text) = self.fields[idx];
+        text.split(sep)
+            .map(|item| {
+                let value = parse_field(self.line, pos, item);
+                pos += item.len() + sep.len();
+                value
+            })
+            .collect()
+    }
+}
+
+/// parse `text` (found at byte offset `pos` of `line`) as a T
+fn parse_field<T: FromStr>(line: &str, pos: usize, text: &str) -> Result<T, ParseError>
+where
+    T::Err: Display,
+{
+    text.parse::<T>().map_err(|e| {
+        ParseError::new(
+            line,
+            column(line, pos),
+            format!(
+                "could not parse \"{text}\" as {}: {e}",
+                std::any::type_name::<T>()
+            ),
+        )
+    })
+}
+
+/// parse a whole line (ignoring surrounding whitespace) as a T
+pub fn parse_value<T: FromStr>(line: &str) -> Result<T, ParseError>
+where
+    T::Err: Display,
+{
+    let text = line.trim();
+    parse_field(line, line.len() - line.trim_start().len(), text)
+}
+
+/// capital letters in the 4x6 font puzzle answers are drawn in, as # and . row by row
+pub const FONT: [(char, &str); 17] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+/// read the letters drawn on a grid of lit pixels (6 rows) in the puzzles' font,
+/// where each letter is 4 pixels wide followed by a blank column. Blank space after
+/// the last letter is ignored.
+pub fn ocr(grid: &[Vec<bool>]) -> Result<String, ParseError> {
+    if grid.len() != 6 {
+        return Err(ParseError::new(
+            "",
+            1,
+            format!("expected 6 rows of pixels, found {}", grid.len()),
+        ));
+    }
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |x: usize, y: usize| grid[y].get(x).copied().unwrap_or(false);
+    // the first row, for errors
+    let top: String = (0..width)
+        .map(|x| if lit(x, 0) { '#' } else { '.' })
+        .collect();
+
+    let mut letters = String::new();
+    for left in (0..width).step_by(5) {
+        let glyph: String = (0..6)
+            .flat_map(|y| (left..left + 4).map(move |x| (x, y)))
+            .map(|(x, y)| if lit(x, y) { '#' } else { '.' })
+            .collect();
+        if !glyph.contains('#') && (left..width).all(|x| (0..6).all(|y| !lit(x, y))) {
+            break; // nothing else drawn
+        }
+        match FONT.iter().find(|(_, pattern)| *pattern == glyph) {
+            Some((letter, _)) => letters.push(*letter),
+            None => {
+                return Err(ParseError::new(
+                    &top,
+                    left + 1,
+                    format!(
+                        "unrecognised letter {}",
+                        glyph
+                            .as_bytes()
+                            .chunks(4)
+                            .map(|row| String::from_utf8_lossy(row))
+                            .join("/")
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(letters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_cycle() {
+        // counter that climbs 1..=4 and wraps around to 1, adding its value to a total
+        // each step: cycle of length 4 starting after the first step
+        let mut state = (0, 0);
+        let cycle = find_cycle(
+            &mut state,
+            100,
+            |(counter, total)| {
+                *counter = *counter % 4 + 1;
+                *total += *counter;
+            },
+            |(counter, _)| *counter,
+            |(_, total)| *total,
+        )
+        .unwrap();
+
+        assert_eq!(cycle.start, 1);
+        assert_eq!(cycle.length, 4);
+        assert_eq!(cycle.value_at(3), 1 + 2 + 3);
+        assert_eq!(cycle.value_at(9), 1 + 2 + 3 + 4 + 1 + 2 + 3 + 4 + 1);
+        assert_eq!(cycle.value_at(1_000_000_001), 1 + 250_000_000 * 10);
+    }
+
+    #[test]
+    fn test_find_cycle_decreasing() {
+        // position bouncing between 0 and 2 with a total that drops by 1 per cycle
+        let mut state = (0, 0);
+        let cycle = find_cycle(
+            &mut state,
+            100,
+            |(position, total)| {
+                *position = (*position + 1) % 3;
+                *total += if *position == 0 { -3 } else { 1 };
+            },
+            |(position, _)| *position,
+            |(_, total)| *total,
+        )
+        .unwrap();
+        assert_eq!((cycle.start, cycle.length), (0, 3));
+        assert_eq!(cycle.value_at(3), -1);
+        assert_eq!(cycle.value_at(3_000_001), -1_000_000 + 1);
+    }
+
+    #[test]
+    fn test_find_cycle_none() {
+        let mut state = 0;
+        let cycle = find_cycle(&mut state, 100, |n| *n += 1, |n| *n, |n| *n);
+        assert_eq!(cycle, None);
+    }
+
+    #[test]
+    fn test_ints() {
+        assert_eq!(
+            ints("Sensor at x=2, y=-18: closest beacon is at x=-2, y=15"),
+            vec![2, -18, -2, 15]
+        );
+        assert_eq!(ints("2-4,6-8"), vec![2, 4, 6, 8]);
+        assert_eq!(ints("no numbers - here"), Vec::<i64>::new());
+
+        assert_eq!(ints_as::<i32>("x=-2, y=15"), Ok(vec![-2, 15]));
+        let err = ints_as::<i32>("x=1, y=-3000000000").unwrap_err();
+        assert_eq!(err.column, 8);
+        assert_eq!(err.message, "-3000000000 is out of range for i32");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let input = "1\n2\n\n3\r\n\r\n\n4\n";
+        assert_eq!(blocks(input), vec![(0, "1\n2"), (3, "3"), (6, "4")]);
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let block = "Monkey 0:\n  Starting items: 79, 98\n  Test divisible by 23";
+        let err = parse_records(block).unwrap_err().offset(10);
+        assert_eq!((err.line, err.column), (13, 23));
+        assert_eq!(
+            parse_records("Monkey 0:\n  Starting items: 79, 98").unwrap(),
+            vec![("Monkey 0", ""), ("Starting items", "79, 98")]
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        let template = Template::new("move {} from {} to {}");
+        let captures = template.captures("move 3 from 1 to 12").unwrap();
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures.get::<u32>(0), Ok(3));
+        assert_eq!(captures.get::<usize>(2), Ok(12));
+
+        let err = template
+            .captures("move x from 1 to 2")
+            .unwrap()
+            .get::<u32>(0);
+        assert_eq!(err.unwrap_err().column, 6);
+
+        let err = template.captures("move 1 to 2").unwrap_err();
+        assert_eq!(err.column, 6);
+        assert_eq!(
+            err.to_string(),
+            "column 6: expected \" from \" (in \"move 1 to 2\")"
+        );
+
+        let template = Template::new("  Starting items: {}");
+        let captures = template.captures("  Starting items: 79, x8").unwrap();
+        assert_eq!(captures.list::<u32>(0, ", ").unwrap_err().column, 23);
+
+        let template = Template::new("[{}]");
+        assert_eq!(template.captures("[a, b]").unwrap().str(0), "a, b");
+        assert!(template.captures("[a, b").is_err());
+
+        // no placeholders: the whole line must match
+        let template = Template::new("noop");
+        assert!(template.captures("noop").unwrap().is_empty());
+        assert_eq!(template.captures("noop 1").unwrap_err().column, 5);
+        assert_eq!(template.captures("nop").unwrap_err().column, 1);
+    }
+
+    #[test]
+    fn test_ocr() {
+        let grid = |text: &str| {
+            text.lines()
+                .map(|line| line.chars().map(|c| c == '#').collect_vec())
+                .collect_vec()
+        };
+        let screen = "\
+#..#..###.####.
+#..#...#..#....
+####...#..###..
+#..#...#..#....
+#..#...#..#....
+#..#..###.#....";
+        assert_eq!(ocr(&grid(screen)), Ok(String::from("HIF")));
+
+        // every letter in the font, and trailing blank space
+        let all = (0..6)
+            .map(|y| {
+                FONT.iter()
+                    .map(|(_, pattern)| format!("{}.", &pattern[4 * y..4 * y + 4]))
+                    .collect::<String>()
+                    + "....."
+            })
+            .join("\n");
+        assert_eq!(ocr(&grid(&all)), Ok(String::from("ABCEFGHIJKLOPRSUZ")));
+
+        let err = ocr(&grid(&screen.replace("#..#..###", "#..#..#.#"))).unwrap_err();
+        assert_eq!(err.column, 6);
+        assert_eq!(
+            err.message,
+            "unrecognised letter .#.#/..#./..#./..#./..#./.#.#"
+        );
+        assert!(ocr(&grid("#..#")).is_err());
+    }
+}
@@ -12,27 +12,51 @@ pub const ANSI_ITALIC: &str = "\x1b[3m";
 pub const ANSI_BOLD: &str = "\x1b[1m";
 pub const ANSI_RESET: &str = "\x1b[0m";
 
+/// return types `solve!` accepts from a solver: `Option<T>` (None = not solved yet),
+/// or `Result<T, E>` for solvers that can reject malformed input.
+pub trait Answer {
+    type Output: std::fmt::Display;
+    fn into_answer(self) -> Result<Option<Self::Output>, String>;
+}
+
+impl<T: std::fmt::Display> Answer for Option<T> {
+    type Output = T;
+    fn into_answer(self) -> Result<Option<T>, String> {
+        Ok(self)
+    }
+}
+
+impl<T: std::fmt::Display, E: std::fmt::Display> Answer for Result<T, E> {
+    type Output = T;
+    fn into_answer(self) -> Result<Option<T>, String> {
+        self.map(Some).map_err(|e| e.to_string())
+    }
+}
+
 #[macro_export]
 macro_rules! solve {
     ($part:expr, $solver:ident, $input:expr) => {{
-        use advent_of_code::{ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
-        use std::fmt::Display;
+        use advent_of_code::{Answer, ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
         use std::time::Instant;
 
-        fn print_result<T: Display>(func: impl FnOnce(&str) -> Option<T>, input: &str) {
+        fn print_result<A: Answer>(func: impl FnOnce(&str) -> A, input: &str) {
             let timer = Instant::now();
-            let result = func(input);
+            let result = func(input).into_answer();
             let elapsed = timer.elapsed();
             match result {
-                Some(result) => {
+                Ok(Some(result)) => {
                     println!(
                         "{} {}(elapsed: {:.2?}){}",
                         result, ANSI_ITALIC, elapsed, ANSI_RESET
                     );
                 }
-                None => {
+                Ok(None) => {
                     println!("not solved.")
                 }
+                Err(e) => {
+                    // the binary name is the (zero-padded) day
+                    println!("invalid input for day {}: {}", env!("CARGO_BIN_NAME"), e)
+                }
             }
         }
 
@@ -41,13 +65,57 @@ macro_rules! solve {
     }};
 }
 
+/// how `read_file` cleans up an input file before it is handed to a solution.
+/// Line endings are always converted to `\n` and a leading BOM is always removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalize {
+    pub trailing_whitespace: bool, // strip whitespace from the end of every line
+    pub trailing_blank_lines: bool, // end the file with exactly one newline
+}
+impl Normalize {
+    pub const ALL: Normalize = Normalize {
+        trailing_whitespace: true,
+        trailing_blank_lines: true,
+    };
+    /// for inputs where whitespace is meaningful (e.g. ascii drawings)
+    pub const LINE_ENDINGS_ONLY: Normalize = Normalize {
+        trailing_whitespace: false,
+        trailing_blank_lines: false,
+    };
+}
+
+pub fn normalize_input(input: &str, normalize: Normalize) -> String {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let input = input.replace("\r\n", "\n").replace('\r', "\n");
+    let mut output = if normalize.trailing_whitespace {
+        input
+            .split('\n')
+            .map(|line| line.trim_end())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    } else {
+        input
+    };
+    if normalize.trailing_blank_lines {
+        output.truncate(output.trim_end_matches('\n').len());
+        if !output.is_empty() {
+            output.push('\n');
+        }
+    }
+    output
+}
+
 pub fn read_file(folder: &str, day: u8) -> String {
+    read_file_with(folder, day, Normalize::ALL)
+}
+
+pub fn read_file_with(folder: &str, day: u8, normalize: Normalize) -> String {
     let cwd = env::current_dir().unwrap();
 
     let filepath = cwd.join("src").join(folder).join(format!("{:02}.txt", day));
 
     let f = fs::read_to_string(filepath);
-    f.expect("could not open input file")
+    normalize_input(&f.expect("could not open input file"), normalize)
 }
 
 fn parse_time(val: &str, postfix: &str) -> f64 {
@@ -122,4 +190,15 @@ mod tests {
             10400.50_f64
         );
     }
+
+    #[test]
+    fn test_normalize_input() {
+        let input = "\u{feff}    [D]    \r\n[N] [C]  \r\n\r\n\r\n";
+        assert_eq!(normalize_input(input, Normalize::ALL), "    [D]\n[N] [C]\n");
+        assert_eq!(
+            normalize_input(input, Normalize::LINE_ENDINGS_ONLY),
+            "    [D]    \n[N] [C]  \n\n\n"
+        );
+        assert_eq!(normalize_input("1\n2", Normalize::ALL), "1\n2\n");
+    }
 }